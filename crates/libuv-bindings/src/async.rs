@@ -1,6 +1,7 @@
 use std::error::Error as StdError;
 
 use libuv_sys2::{self as ffi, uv_async_t};
+use luajit_bindings as lua;
 
 use crate::{Error, Handle};
 
@@ -77,8 +78,20 @@ extern "C" fn async_cb(ptr: *mut uv_async_t) {
     if !callback.is_null() {
         let callback = unsafe { &mut *callback };
 
-        if let Err(_err) = callback() {
-            // TODO: what now?
+        match lua::utils::catch_panic(callback) {
+            Ok(Ok(())) => {},
+
+            Ok(Err(err)) => unsafe {
+                lua::with_state(|lstate| {
+                    lua::utils::schedule_error(lstate, &*err)
+                })
+            },
+
+            Err(panic) => unsafe {
+                lua::with_state(|lstate| {
+                    lua::utils::schedule_error(lstate, &panic)
+                })
+            },
         }
     }
 }
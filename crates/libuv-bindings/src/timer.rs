@@ -2,6 +2,7 @@ use std::error::Error as StdError;
 use std::time::Duration;
 
 use libuv_sys2::{self as ffi, uv_timer_t};
+use luajit_bindings as lua;
 
 use crate::{Error, Handle};
 
@@ -101,8 +102,20 @@ extern "C" fn timer_cb(ptr: *mut uv_timer_t) {
         let mut handle = TimerHandle { handle };
         let callback = unsafe { &mut *callback };
 
-        if let Err(_err) = callback(&mut handle) {
-            // TODO: what now?
+        match lua::utils::catch_panic(|| callback(&mut handle)) {
+            Ok(Ok(())) => {},
+
+            Ok(Err(err)) => unsafe {
+                lua::with_state(|lstate| {
+                    lua::utils::schedule_error(lstate, &*err)
+                })
+            },
+
+            Err(panic) => unsafe {
+                lua::with_state(|lstate| {
+                    lua::utils::schedule_error(lstate, &panic)
+                })
+            },
         }
     }
 }
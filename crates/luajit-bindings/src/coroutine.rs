@@ -0,0 +1,198 @@
+use std::ffi::c_int;
+use std::fmt;
+
+use crate::ffi::{self, lua_State};
+use crate::{function, Error, Poppable, Pushable, RegistryRef};
+
+/// A handle to a Lua coroutine, i.e. a value of type `thread`.
+///
+/// Coroutines can be created from Rust with [`Coroutine::new`] or popped off
+/// the stack after being created in Lua with `coroutine.create`, and can be
+/// driven from Rust with [`resume`](Coroutine::resume).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Coroutine {
+    lua_ref: RegistryRef,
+}
+
+/// The status of a [`Coroutine`], as returned by Lua's `coroutine.status`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CoroutineStatus {
+    /// The coroutine hasn't started yet or it's yielded, and can be resumed.
+    Suspended,
+
+    /// The coroutine is running, either directly or because it resumed
+    /// another coroutine.
+    Running,
+
+    /// The coroutine has returned or raised an error, and can't be resumed
+    /// anymore.
+    Dead,
+}
+
+/// The return value of a Rust function that can yield, created with
+/// [`function::store_yielding`].
+///
+/// When a coroutine suspended by a Rust function is resumed, the values it's
+/// resumed with are returned to the function's Lua caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Yielding<Y, R> {
+    /// Yield the values to whoever resumed the current coroutine.
+    Yield(Y),
+
+    /// Return the values to the caller like a normal function.
+    Return(R),
+}
+
+impl fmt::Debug for Coroutine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<thread {}>", self.lua_ref.as_raw())
+    }
+}
+
+impl Coroutine {
+    /// Creates a new suspended coroutine which will call `fun` when it's
+    /// first resumed.
+    pub fn new<F: Pushable>(fun: F) -> Result<Self, Error> {
+        unsafe {
+            crate::with_state(move |lstate| {
+                let top = ffi::lua_gettop(lstate);
+
+                let thread = ffi::lua_newthread(lstate);
+                let lua_ref = RegistryRef::from_stack(lstate);
+
+                if let Err(err) = fun.push(lstate) {
+                    ffi::lua_settop(lstate, top);
+                    return Err(err);
+                }
+
+                if ffi::lua_type(lstate, -1) != ffi::LUA_TFUNCTION {
+                    let err = Error::push_error(
+                        std::any::type_name::<F>(),
+                        format!(
+                            "coroutines must be created from a function, got \
+                             {}",
+                            crate::utils::inspect_with_depth(lstate, -1, 0)
+                        ),
+                    );
+                    ffi::lua_settop(lstate, top);
+                    return Err(err);
+                }
+
+                ffi::lua_xmove(lstate, thread, 1);
+                Ok(Self { lua_ref })
+            })
+        }
+    }
+
+    /// Resumes the coroutine with the given arguments, returning the values
+    /// it yielded or, if it finished, the values it returned.
+    pub fn resume<A, R>(&self, args: A) -> Result<R, Error>
+    where
+        A: Pushable,
+        R: Poppable,
+    {
+        unsafe {
+            crate::with_state(move |lstate| {
+                let thread = self.thread(lstate);
+
+                match status(thread) {
+                    CoroutineStatus::Suspended => {},
+                    CoroutineStatus::Running => {
+                        return Err(Error::runtime_error(
+                            "cannot resume non-suspended coroutine",
+                        ))
+                    },
+                    CoroutineStatus::Dead => {
+                        return Err(Error::runtime_error(
+                            "cannot resume dead coroutine",
+                        ))
+                    },
+                }
+
+                let top = ffi::lua_gettop(thread);
+
+                let nargs = match args.push(thread) {
+                    Ok(nargs) => nargs,
+                    Err(err) => {
+                        ffi::lua_settop(thread, top);
+                        return Err(err);
+                    },
+                };
+
+                match ffi::lua_resume(thread, nargs) {
+                    ffi::LUA_OK | ffi::LUA_YIELD => {
+                        let ret = R::pop(thread);
+                        // Clear any value that wasn't popped, so that the
+                        // stack only contains the arguments of the next
+                        // `resume`.
+                        ffi::lua_settop(thread, 0);
+                        ret
+                    },
+
+                    err_code => Err(function::pop_error(thread, err_code)),
+                }
+            })
+        }
+    }
+
+    /// Returns the status of the coroutine.
+    pub fn status(&self) -> CoroutineStatus {
+        unsafe { crate::with_state(|lstate| status(self.thread(lstate))) }
+    }
+
+    /// Returns the coroutine's Lua thread, which stays alive for as long as
+    /// the `Coroutine` does.
+    unsafe fn thread(&self, lstate: *mut lua_State) -> *mut lua_State {
+        self.lua_ref.push_value(lstate);
+        let thread = ffi::lua_tothread(lstate, -1);
+        ffi::lua_pop(lstate, 1);
+        thread
+    }
+}
+
+/// Same logic as `coroutine.status`, except that it can't tell the running
+/// coroutine apart from the ones that resumed another coroutine.
+unsafe fn status(thread: *mut lua_State) -> CoroutineStatus {
+    match ffi::lua_status(thread) {
+        ffi::LUA_YIELD => CoroutineStatus::Suspended,
+
+        ffi::LUA_OK => {
+            let mut ar = ffi::lua_Debug::default();
+            if ffi::lua_getstack(thread, 0, &mut ar) > 0 {
+                CoroutineStatus::Running
+            } else if ffi::lua_gettop(thread) == 0 {
+                CoroutineStatus::Dead
+            } else {
+                CoroutineStatus::Suspended
+            }
+        },
+
+        _ => CoroutineStatus::Dead,
+    }
+}
+
+impl Poppable for Coroutine {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        match ffi::lua_type(lstate, -1) {
+            ffi::LUA_TTHREAD => {
+                Ok(Self { lua_ref: RegistryRef::from_stack(lstate) })
+            },
+
+            _ => Err(Error::pop_wrong_value::<Self>(
+                lstate,
+                -1,
+                ffi::LUA_TTHREAD,
+            )),
+        }
+    }
+}
+
+impl Pushable for Coroutine {
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        self.lua_ref.push(lstate)
+    }
+}
@@ -2,15 +2,20 @@ use std::ffi::c_int;
 
 use thiserror::Error as ThisError;
 
-use crate::utils;
+use crate::ffi::lua_State;
+use crate::{utils, Path, PathSegment};
+
+/// How deep to go when showing nested tables in error messages.
+const INSPECT_DEPTH: usize = 2;
 
 #[derive(Clone, Debug, Eq, PartialEq, ThisError, Hash)]
 pub enum Error {
     #[error(
-        "Value of type {ty} couldn't be popped from the stack{}.",
+        "Value of type {ty} couldn't be popped from the stack{}{}.",
+        if path.is_empty() { String::new() } else { format!(" at `{path}`") },
         message.as_ref().map(|msg| format!(": {msg}")).unwrap_or_default()
     )]
-    PopError { ty: &'static str, message: Option<String> },
+    PopError { ty: &'static str, message: Option<String>, path: Path },
 
     #[error(
         "Value of type {ty} couldn't be pushed on the stack{}.",
@@ -18,25 +23,43 @@ pub enum Error {
     )]
     PushError { ty: &'static str, message: Option<String> },
 
-    #[error("Lua runtime error: {0}")]
-    RuntimeError(String),
+    #[error(
+        "Lua runtime error: {message}{}",
+        traceback.as_ref().map(|tb| format!("\n{tb}")).unwrap_or_default()
+    )]
+    RuntimeError { message: String, traceback: Option<String> },
+
+    #[error("Lua syntax error: {0}")]
+    SyntaxError(String),
 
     #[error("Lua memory error: {0}")]
     MemoryError(String),
 
-    #[error("TODO")]
+    #[error("Tried to pop a value from an empty stack.")]
     PopEmptyStack,
+
+    #[error("Lua code ran past its budget.")]
+    Timeout,
 }
 
 impl Error {
     pub fn pop_error<M: Into<String>>(ty: &'static str, message: M) -> Self {
-        Self::PopError { ty, message: Some(message.into()) }
+        Self::PopError {
+            ty,
+            message: Some(message.into()),
+            path: Path::default(),
+        }
+    }
+
+    pub fn runtime_error<M: Into<String>>(message: M) -> Self {
+        Self::RuntimeError { message: message.into(), traceback: None }
     }
 
     pub fn pop_error_from_err<T, E: std::error::Error>(err: E) -> Self {
         Self::PopError {
             ty: std::any::type_name::<T>(),
             message: Some(err.to_string()),
+            path: Path::default(),
         }
     }
 
@@ -50,6 +73,25 @@ impl Error {
                 "expected a {}, found a {} instead",
                 expected, found
             )),
+            path: Path::default(),
+        }
+    }
+
+    /// Same as [`pop_wrong_type`](Error::pop_wrong_type), but the message
+    /// also shows the value at the given stack index.
+    pub unsafe fn pop_wrong_value<T>(
+        lstate: *mut lua_State,
+        idx: c_int,
+        expected: c_int,
+    ) -> Self {
+        Self::PopError {
+            ty: std::any::type_name::<T>(),
+            message: Some(format!(
+                "expected a {}, found {} instead",
+                utils::type_name(expected),
+                utils::inspect_with_depth(lstate, idx, INSPECT_DEPTH),
+            )),
+            path: Path::default(),
         }
     }
 
@@ -58,7 +100,7 @@ impl Error {
         idx: std::ffi::c_int,
     ) -> Self {
         let expected = std::any::type_name::<T>();
-        let got = crate::utils::debug_type(lstate, idx);
+        let got = utils::inspect_with_depth(lstate, idx, INSPECT_DEPTH);
 
         Self::PopError {
             ty: expected,
@@ -66,6 +108,39 @@ impl Error {
                 "expected {}, got {} instead",
                 expected, got
             )),
+            path: Path::default(),
+        }
+    }
+
+    /// Adds a segment at the start of the path of a
+    /// [`PopError`](Error::PopError). Any other error is returned unchanged.
+    pub fn in_segment(mut self, segment: PathSegment) -> Self {
+        if let Self::PopError { path, .. } = &mut self {
+            path.prepend(segment);
+        }
+        self
+    }
+
+    /// Records that the error happened while popping the value of the
+    /// string key `field`.
+    pub fn in_field<F: Into<String>>(self, field: F) -> Self {
+        self.in_segment(PathSegment::Field(field.into()))
+    }
+
+    /// Records that the error happened while popping the value at index
+    /// `idx` of an array.
+    pub fn at_index(self, idx: i64) -> Self {
+        self.in_segment(PathSegment::Index(idx))
+    }
+
+    /// Records that the error happened while popping the value of the table
+    /// key at the given stack index.
+    pub unsafe fn at_key(self, lstate: *mut lua_State, idx: c_int) -> Self {
+        match self {
+            Self::PopError { .. } => {
+                self.in_segment(PathSegment::from_key(lstate, idx))
+            },
+            other => other,
         }
     }
 
@@ -24,9 +24,15 @@ pub const fn lua_upvalueindex(i: c_int) -> c_int {
     LUA_GLOBALSINDEX - i
 }
 
+// Special references.
+pub const LUA_NOREF: c_int = -2;
+pub const LUA_REFNIL: c_int = -1;
+
 // Thread status.
 pub const LUA_OK: c_int = 0;
+pub const LUA_YIELD: c_int = 1;
 pub const LUA_ERRRUN: c_int = 2;
+pub const LUA_ERRSYNTAX: c_int = 3;
 pub const LUA_ERRMEM: c_int = 4;
 pub const LUA_ERRERR: c_int = 5;
 
@@ -41,10 +47,68 @@ pub const LUA_TTABLE: c_int = 5;
 pub const LUA_TFUNCTION: c_int = 6;
 pub const LUA_TUSERDATA: c_int = 7;
 pub const LUA_TTHREAD: c_int = 8;
+// https://github.com/LuaJIT/LuaJIT/blob/v2.1/src/lj_obj.h
+pub const LUA_TCDATA: c_int = 10;
+
+// Garbage collection options.
+pub const LUA_GCSTOP: c_int = 0;
+pub const LUA_GCRESTART: c_int = 1;
+pub const LUA_GCCOLLECT: c_int = 2;
+pub const LUA_GCCOUNT: c_int = 3;
+pub const LUA_GCCOUNTB: c_int = 4;
+pub const LUA_GCSTEP: c_int = 5;
+pub const LUA_GCSETPAUSE: c_int = 6;
+pub const LUA_GCSETSTEPMUL: c_int = 7;
+// https://github.com/LuaJIT/LuaJIT/blob/v2.1/src/lua.h
+pub const LUA_GCISRUNNING: c_int = 9;
+
+// Event codes.
+pub const LUA_HOOKCALL: c_int = 0;
+pub const LUA_HOOKRET: c_int = 1;
+pub const LUA_HOOKLINE: c_int = 2;
+pub const LUA_HOOKCOUNT: c_int = 3;
+pub const LUA_HOOKTAILRET: c_int = 4;
+
+// Event masks.
+pub const LUA_MASKCALL: c_int = 1 << LUA_HOOKCALL;
+pub const LUA_MASKRET: c_int = 1 << LUA_HOOKRET;
+pub const LUA_MASKLINE: c_int = 1 << LUA_HOOKLINE;
+pub const LUA_MASKCOUNT: c_int = 1 << LUA_HOOKCOUNT;
 
 // https://www.lua.org/manual/5.1/manual.html#lua_CFunction
 pub type lua_CFunction = unsafe extern "C" fn(L: *mut lua_State) -> c_int;
 
+// https://www.lua.org/manual/5.1/manual.html#lua_Hook
+pub type lua_Hook =
+    unsafe extern "C" fn(L: *mut lua_State, ar: *mut lua_Debug);
+
+// https://www.lua.org/manual/5.1/manual.html#lua_Debug
+#[repr(C)]
+pub struct lua_Debug {
+    pub event: c_int,
+    pub name: *const c_char,
+    pub namewhat: *const c_char,
+    pub what: *const c_char,
+    pub source: *const c_char,
+    pub currentline: c_int,
+    pub nups: c_int,
+    pub linedefined: c_int,
+    pub lastlinedefined: c_int,
+    pub short_src: [c_char; LUA_IDSIZE],
+    i_ci: c_int,
+}
+
+impl Default for lua_Debug {
+    fn default() -> Self {
+        // SAFETY: all the fields are either integers or raw pointers, for
+        // which all zeroes is a valid bit pattern.
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+// https://github.com/LuaJIT/LuaJIT/blob/v2.1/src/luaconf.h
+pub const LUA_IDSIZE: usize = 60;
+
 // https://www.lua.org/manual/5.1/manual.html#lua_Integer
 pub type lua_Integer = isize;
 
@@ -55,21 +119,52 @@ extern "C" {
     // https://www.lua.org/manual/5.1/manual.html#lua_call
     pub fn lua_call(L: *mut lua_State, nargs: c_int, nresults: c_int);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_checkstack
+    pub fn lua_checkstack(L: *mut lua_State, extra: c_int) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_createtable
     pub fn lua_createtable(L: *mut lua_State, narr: c_int, nrec: c_int);
 
     // https://www.lua.org/manual/5.1/manual.html#lua_error
     pub fn lua_error(L: *mut lua_State) -> !;
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_gc
+    pub fn lua_gc(L: *mut lua_State, what: c_int, data: c_int) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_call
     pub fn lua_getfield(L: *mut lua_State, index: c_int, k: *const c_char);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_gethook
+    pub fn lua_gethook(L: *mut lua_State) -> Option<lua_Hook>;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_gethookcount
+    pub fn lua_gethookcount(L: *mut lua_State) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_gethookmask
+    pub fn lua_gethookmask(L: *mut lua_State) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_getmetatable
     pub fn lua_getmetatable(L: *mut lua_State, index: c_int) -> c_int;
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_getstack
+    pub fn lua_getstack(
+        L: *mut lua_State,
+        level: c_int,
+        ar: *mut lua_Debug,
+    ) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_gettable
+    pub fn lua_gettable(L: *mut lua_State, index: c_int);
+
     // https://www.lua.org/manual/5.1/manual.html#lua_gettop
     pub fn lua_gettop(L: *mut lua_State) -> c_int;
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_insert
+    pub fn lua_insert(L: *mut lua_State, index: c_int);
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_newthread
+    pub fn lua_newthread(L: *mut lua_State) -> *mut lua_State;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_newuserdata
     pub fn lua_newuserdata(L: *mut lua_State, size: usize) -> *mut c_void;
 
@@ -111,9 +206,22 @@ extern "C" {
     // https://www.lua.org/manual/5.1/manual.html#lua_pushstring
     pub fn lua_pushstring(L: *mut lua_State, s: *const c_char);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_pushthread
+    pub fn lua_pushthread(L: *mut lua_State) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_pushvalue
     pub fn lua_pushvalue(L: *mut lua_State, index: c_int);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_rawequal
+    pub fn lua_rawequal(
+        L: *mut lua_State,
+        index1: c_int,
+        index2: c_int,
+    ) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_rawget
+    pub fn lua_rawget(L: *mut lua_State, index: c_int);
+
     // https://www.lua.org/manual/5.1/manual.html#lua_rawgeti
     pub fn lua_rawgeti(L: *mut lua_State, index: c_int, n: c_int);
 
@@ -123,9 +231,35 @@ extern "C" {
     // https://www.lua.org/manual/5.1/manual.html#lua_rawseti
     pub fn lua_rawseti(L: *mut lua_State, index: c_int, n: c_int);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_remove
+    pub fn lua_remove(L: *mut lua_State, index: c_int);
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_resume
+    pub fn lua_resume(L: *mut lua_State, narg: c_int) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_setfield
+    pub fn lua_setfield(L: *mut lua_State, index: c_int, k: *const c_char);
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_sethook
+    pub fn lua_sethook(
+        L: *mut lua_State,
+        f: Option<lua_Hook>,
+        mask: c_int,
+        count: c_int,
+    ) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_setmetatable
+    pub fn lua_setmetatable(L: *mut lua_State, index: c_int) -> c_int;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_settable
+    pub fn lua_settable(L: *mut lua_State, index: c_int);
+
     // https://www.lua.org/manual/5.1/manual.html#lua_settop
     pub fn lua_settop(L: *mut lua_State, index: c_int);
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_status
+    pub fn lua_status(L: *mut lua_State) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_toboolean
     pub fn lua_toboolean(L: *mut lua_State, index: c_int) -> c_int;
 
@@ -142,6 +276,12 @@ extern "C" {
     // https://www.lua.org/manual/5.1/manual.html#lua_tonumber
     pub fn lua_tonumber(L: *mut lua_State, index: c_int) -> lua_Number;
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_topointer
+    pub fn lua_topointer(L: *mut lua_State, index: c_int) -> *const c_void;
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_tothread
+    pub fn lua_tothread(L: *mut lua_State, index: c_int) -> *mut lua_State;
+
     // https://www.lua.org/manual/5.1/manual.html#lua_touserdata
     pub fn lua_touserdata(L: *mut lua_State, index: c_int) -> *mut c_void;
 
@@ -151,11 +291,25 @@ extern "C" {
     // https://www.lua.org/manual/5.1/manual.html#lua_typename
     pub fn lua_typename(L: *mut lua_State, tp: c_int) -> *const c_char;
 
+    // https://www.lua.org/manual/5.1/manual.html#lua_xmove
+    pub fn lua_xmove(from: *mut lua_State, to: *mut lua_State, n: c_int);
+
+    // https://www.lua.org/manual/5.1/manual.html#lua_yield
+    pub fn lua_yield(L: *mut lua_State, nresults: c_int) -> c_int;
+
     // Lua auxiliary library.
 
     // https://www.lua.org/manual/5.1/manual.html#luaL_error
     pub fn luaL_error(L: *mut lua_State, fmt: *const c_char, ...) -> !;
 
+    // https://www.lua.org/manual/5.1/manual.html#luaL_loadbuffer
+    pub fn luaL_loadbuffer(
+        L: *mut lua_State,
+        buff: *const c_char,
+        sz: usize,
+        name: *const c_char,
+    ) -> c_int;
+
     // https://www.lua.org/manual/5.1/manual.html#luaL_ref
     pub fn luaL_ref(L: *mut lua_State, t: c_int) -> c_int;
 
@@ -168,6 +322,11 @@ pub unsafe fn lua_getglobal(L: *mut lua_State, name: *const c_char) {
     lua_getfield(L, LUA_GLOBALSINDEX, name)
 }
 
+// https://www.lua.org/manual/5.1/manual.html#lua_setglobal
+pub unsafe fn lua_setglobal(L: *mut lua_State, name: *const c_char) {
+    lua_setfield(L, LUA_GLOBALSINDEX, name)
+}
+
 // https://www.lua.org/manual/5.1/manual.html#lua_pop
 pub unsafe fn lua_pop(L: *mut lua_State, n: c_int) {
     lua_settop(L, -n - 1)
@@ -4,49 +4,182 @@ use std::mem;
 use std::ptr;
 
 use crate::ffi::{self, lua_State};
-use crate::{utils, Poppable, Pushable};
+use crate::hook::{self, Budget};
+use crate::{
+    utils,
+    BorrowedArgs,
+    Poppable,
+    Pushable,
+    RegistryRef,
+    StackGuard,
+    Yielding,
+};
 
-/// Stores a function in the Lua registry, returning its ref.
-pub fn store<F, A, R, E>(fun: F) -> c_int
+/// A Rust callback invoked from Lua. It's responsible for popping its
+/// arguments off the stack and pushing its return values, returning how many
+/// there are and whether they should be yielded.
+pub(crate) type Callback =
+    Box<dyn Fn(*mut lua_State) -> Result<Returns, crate::Error> + 'static>;
+
+/// The values pushed on the stack by a [`Callback`].
+pub(crate) enum Returns {
+    /// Return the values to the caller.
+    Return(c_int),
+
+    /// Yield the values to whoever resumed the current coroutine.
+    Yield(c_int),
+}
+
+impl From<c_int> for Returns {
+    #[inline]
+    fn from(nresults: c_int) -> Self {
+        Self::Return(nresults)
+    }
+}
+
+/// Stores a function in the Lua registry, returning a reference to it.
+pub fn store<F, A, R, E>(fun: F) -> RegistryRef
 where
     F: Fn(A) -> Result<R, E> + 'static,
     A: Poppable,
     R: Pushable,
     E: Error + 'static,
 {
-    type Callback =
-        Box<dyn Fn(*mut lua_State) -> Result<c_int, crate::Error> + 'static>;
-
-    unsafe extern "C" fn c_fun(lstate: *mut lua_State) -> c_int {
-        let fun = {
-            let idx = ffi::lua_upvalueindex(1);
-            let upv = ffi::lua_touserdata(lstate, idx) as *mut Callback;
-            &**upv
-        };
+    unsafe {
+        crate::with_state(move |lstate| {
+            let fun = move |lstate| {
+                let args = A::pop(lstate)?;
+                let ret = fun(args)
+                    .map_err(crate::Error::push_error_from_err::<R, _>)?;
+                ret.push(lstate).map(Returns::Return)
+            };
 
-        fun(lstate).unwrap_or_else(|err| utils::handle_error(lstate, &err))
+            push_callback(lstate, Box::new(fun));
+            RegistryRef::from_stack(lstate)
+        })
     }
+}
 
+/// Same as [`store`], but the function's arguments borrow the strings passed
+/// by Lua instead of copying them. See [`BorrowedArgs`] for how they're
+/// specified.
+pub fn store_borrowed<F, A, R, E>(fun: F) -> RegistryRef
+where
+    F: for<'a> Fn(<A as BorrowedArgs<'a>>::Args) -> Result<R, E> + 'static,
+    A: for<'a> BorrowedArgs<'a>,
+    R: Pushable,
+    E: Error + 'static,
+{
     unsafe {
         crate::with_state(move |lstate| {
             let fun = move |lstate| {
-                let args = A::pop(lstate)?;
+                // The arguments stay alive until the guard is dropped, after
+                // the function has returned.
+                let guard = StackGuard::new(lstate);
+                let args = guard.pop::<<A as BorrowedArgs>::Args>()?;
                 let ret = fun(args)
                     .map_err(crate::Error::push_error_from_err::<R, _>)?;
-                ret.push(lstate)
+                ret.push(lstate).map(Returns::Return)
             };
 
-            let ud = ffi::lua_newuserdata(lstate, mem::size_of::<Callback>());
-            ptr::write(ud as *mut Callback, Box::new(fun));
+            push_callback(lstate, Box::new(fun));
+            RegistryRef::from_stack(lstate)
+        })
+    }
+}
+
+/// Same as [`store`], but the function can also yield values back to
+/// whoever resumed the coroutine it's running in.
+pub fn store_yielding<F, A, Y, R, E>(fun: F) -> RegistryRef
+where
+    F: Fn(A) -> Result<Yielding<Y, R>, E> + 'static,
+    A: Poppable,
+    Y: Pushable,
+    R: Pushable,
+    E: Error + 'static,
+{
+    unsafe {
+        crate::with_state(move |lstate| {
+            let fun = move |lstate| {
+                let args = A::pop(lstate)?;
+                match fun(args)
+                    .map_err(crate::Error::push_error_from_err::<R, _>)?
+                {
+                    Yielding::Yield(values) => {
+                        values.push(lstate).map(Returns::Yield)
+                    },
+                    Yielding::Return(values) => {
+                        values.push(lstate).map(Returns::Return)
+                    },
+                }
+            };
 
-            ffi::lua_pushcclosure(lstate, c_fun, 1);
-            ffi::luaL_ref(lstate, ffi::LUA_REGISTRYINDEX)
+            push_callback(lstate, Box::new(fun));
+            RegistryRef::from_stack(lstate)
         })
     }
 }
 
+/// Pushes a C closure executing `callback` on top of the stack.
+pub(crate) unsafe fn push_callback(
+    lstate: *mut lua_State,
+    callback: Callback,
+) {
+    unsafe extern "C" fn c_fun(lstate: *mut lua_State) -> c_int {
+        let fun = {
+            let idx = ffi::lua_upvalueindex(1);
+            let upv = ffi::lua_touserdata(lstate, idx) as *mut Callback;
+            &**upv
+        };
+
+        match utils::catch_panic(|| fun(lstate)).and_then(|res| res) {
+            Ok(Returns::Return(nresults)) => nresults,
+            Ok(Returns::Yield(nresults)) => ffi::lua_yield(lstate, nresults),
+            Err(err) => utils::handle_error(lstate, &err),
+        }
+    }
+
+    let ud = ffi::lua_newuserdata(lstate, mem::size_of::<Callback>());
+    ptr::write(ud as *mut Callback, callback);
+    ffi::lua_pushcclosure(lstate, c_fun, 1);
+}
+
 /// Calls a function previously stored in the Lua registry via [store].
 pub fn call<A, R>(lua_ref: c_int, args: A) -> Result<R, crate::Error>
+where
+    A: Pushable,
+    R: Poppable,
+{
+    call_inner(lua_ref, args, false)
+}
+
+/// Same as [`call`], but if the function raises an error the Lua traceback
+/// at the point where the error was raised is included in the returned
+/// [`RuntimeError`](crate::Error::RuntimeError).
+pub fn call_with_traceback<A, R>(
+    lua_ref: c_int,
+    args: A,
+) -> Result<R, crate::Error>
+where
+    A: Pushable,
+    R: Poppable,
+{
+    call_inner(lua_ref, args, true)
+}
+
+/// Same as [`call`], but returns a [`Timeout`](crate::Error::Timeout) error
+/// if the function runs past the given budget.
+///
+/// While the function is running any hook set with [`hook::set`] is replaced
+/// by the one enforcing the budget. Also, since LuaJIT doesn't run hooks from
+/// JIT-compiled code, JIT compilation is disabled for the function and the
+/// ones defined within it for the duration of the call, and re-enabled once
+/// it returns if the JIT compiler was on.
+pub fn call_with_budget<A, R>(
+    lua_ref: c_int,
+    args: A,
+    budget: Budget,
+) -> Result<R, crate::Error>
 where
     A: Pushable,
     R: Poppable,
@@ -54,42 +187,185 @@ where
     unsafe {
         crate::with_state(move |lstate| {
             ffi::lua_rawgeti(lstate, ffi::LUA_REGISTRYINDEX, lua_ref);
-            let nargs = args.push(lstate)?;
+            let jit_was_on = set_jit(lstate, false);
 
-            match ffi::lua_pcall(lstate, nargs, -1, 0 /* <- errorfunc */) {
-                ffi::LUA_OK => R::pop(lstate),
+            let res = hook::with_budget(lstate, budget, || {
+                pcall(lstate, args, false)
+            });
 
-                err_code => {
-                    let msg = CStr::from_ptr(ffi::lua_tostring(lstate, -1))
-                        .to_string_lossy()
-                        .to_string();
-
-                    ffi::lua_pop(lstate, 1);
+            if jit_was_on {
+                ffi::lua_rawgeti(lstate, ffi::LUA_REGISTRYINDEX, lua_ref);
+                set_jit(lstate, true);
+                ffi::lua_pop(lstate, 1);
+            }
 
-                    match err_code {
-                        ffi::LUA_ERRRUN => {
-                            Err(crate::Error::RuntimeError(msg))
-                        },
+            res
+        })
+    }
+}
 
-                        ffi::LUA_ERRMEM => Err(crate::Error::MemoryError(msg)),
+/// Calls `jit.on(f, true)` or `jit.off(f, true)` on the function `f` at the
+/// top of the stack, ignoring any error. Returns whether the JIT compiler was
+/// on before the call.
+unsafe fn set_jit(lstate: *mut lua_State, on: bool) -> bool {
+    let top = ffi::lua_gettop(lstate);
+    let mut was_on = false;
 
-                        ffi::LUA_ERRERR => {
-                            unreachable!("errorfunc is 0, this never happens!")
-                        },
+    ffi::lua_getglobal(lstate, crate::macros::cstr!("jit"));
+    if ffi::lua_type(lstate, -1) == ffi::LUA_TTABLE {
+        ffi::lua_getfield(lstate, -1, crate::macros::cstr!("status"));
+        if ffi::lua_pcall(lstate, 0, 1, 0) == ffi::LUA_OK {
+            was_on = ffi::lua_toboolean(lstate, -1) != 0;
+        }
+        ffi::lua_pop(lstate, 1);
 
-                        _ => unreachable!(),
-                    }
-                },
-            }
-        })
+        let toggle = if on {
+            crate::macros::cstr!("on")
+        } else {
+            crate::macros::cstr!("off")
+        };
+        ffi::lua_getfield(lstate, -1, toggle);
+        ffi::lua_pushvalue(lstate, top);
+        ffi::lua_pushboolean(lstate, 1);
+        ffi::lua_pcall(lstate, 2, 0, 0);
     }
+
+    ffi::lua_settop(lstate, top);
+
+    was_on
 }
 
-/// Removes the function reference stored in the Lua registry
-pub fn remove(lua_ref: c_int) {
+fn call_inner<A, R>(
+    lua_ref: c_int,
+    args: A,
+    with_traceback: bool,
+) -> Result<R, crate::Error>
+where
+    A: Pushable,
+    R: Poppable,
+{
     unsafe {
-        crate::with_state(|lstate| {
-            ffi::luaL_unref(lstate, ffi::LUA_REGISTRYINDEX, lua_ref)
+        crate::with_state(move |lstate| {
+            ffi::lua_rawgeti(lstate, ffi::LUA_REGISTRYINDEX, lua_ref);
+            pcall(lstate, args, with_traceback)
         })
     }
 }
+
+/// Calls the function at the top of the stack in protected mode, popping it
+/// together with its return values. The stack is always left as it was
+/// before the function was pushed.
+pub(crate) unsafe fn pcall<A, R>(
+    lstate: *mut lua_State,
+    args: A,
+    with_traceback: bool,
+) -> Result<R, crate::Error>
+where
+    A: Pushable,
+    R: Poppable,
+{
+    let top = ffi::lua_gettop(lstate) - 1;
+
+    let errorfunc = if with_traceback {
+        ffi::lua_pushcfunction(lstate, traceback_handler);
+        ffi::lua_insert(lstate, -2);
+        top + 1
+    } else {
+        0
+    };
+
+    let nargs = match args.push(lstate) {
+        Ok(nargs) => nargs,
+        Err(err) => {
+            ffi::lua_settop(lstate, top);
+            return Err(err);
+        },
+    };
+
+    let res = ffi::lua_pcall(lstate, nargs, -1, errorfunc);
+
+    if errorfunc != 0 {
+        ffi::lua_remove(lstate, errorfunc);
+    }
+
+    let ret = match res {
+        // Only the values returned by the function can be popped, and any
+        // extra ones `R` doesn't take are discarded below.
+        ffi::LUA_OK => R::pop_reserving(lstate, top),
+
+        ffi::LUA_ERRRUN if with_traceback => {
+            // The handler turned the error into a `{ msg, traceback }` table.
+            ffi::lua_rawgeti(lstate, -1, 1);
+            let message = error_message(lstate);
+            ffi::lua_rawgeti(lstate, -2, 2);
+            let traceback = (ffi::lua_type(lstate, -1) == ffi::LUA_TSTRING)
+                .then(|| error_message(lstate).trim_start().to_owned());
+            Err(crate::Error::RuntimeError { message, traceback })
+        },
+
+        err_code => Err(pop_error(lstate, err_code)),
+    };
+
+    ffi::lua_settop(lstate, top);
+
+    ret
+}
+
+/// Pops the error at the top of the stack, given the status code returned
+/// by the function that raised it.
+pub(crate) unsafe fn pop_error(
+    lstate: *mut lua_State,
+    err_code: c_int,
+) -> crate::Error {
+    let msg = error_message(lstate);
+    ffi::lua_pop(lstate, 1);
+
+    match err_code {
+        ffi::LUA_ERRRUN => crate::Error::runtime_error(msg),
+
+        ffi::LUA_ERRMEM => crate::Error::MemoryError(msg),
+
+        ffi::LUA_ERRERR => crate::Error::runtime_error(format!(
+            "error in error handling: {msg}"
+        )),
+
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the error message at the top of the stack without popping it.
+pub(crate) unsafe fn error_message(lstate: *mut lua_State) -> String {
+    let ptr = ffi::lua_tostring(lstate, -1);
+
+    if ptr.is_null() {
+        format!("(error object is a {} value)", utils::debug_type(lstate, -1))
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+/// The message handler used by [`call_with_traceback`]. It replaces the
+/// error with a `{ err, debug.traceback() }` table.
+unsafe extern "C" fn traceback_handler(lstate: *mut lua_State) -> c_int {
+    // Stack: [err].
+    ffi::lua_createtable(lstate, 2, 0);
+    ffi::lua_insert(lstate, 1);
+    ffi::lua_rawseti(lstate, 1, 1);
+
+    ffi::lua_getglobal(lstate, crate::macros::cstr!("debug"));
+    if ffi::lua_type(lstate, -1) == ffi::LUA_TTABLE {
+        ffi::lua_getfield(lstate, -1, crate::macros::cstr!("traceback"));
+        if ffi::lua_type(lstate, -1) == ffi::LUA_TFUNCTION {
+            // Passing an empty message as `nil` would make LuaJIT return it
+            // unchanged. Level 2 skips this handler.
+            ffi::lua_pushlstring(lstate, "".as_ptr() as *const _, 0);
+            ffi::lua_pushinteger(lstate, 2);
+            if ffi::lua_pcall(lstate, 2, 1, 0) == ffi::LUA_OK {
+                ffi::lua_rawseti(lstate, 1, 2);
+            }
+        }
+    }
+
+    ffi::lua_settop(lstate, 1);
+    1
+}
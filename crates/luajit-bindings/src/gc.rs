@@ -0,0 +1,66 @@
+//! Functions to control Lua's garbage collector, see the documentation of
+//! [`collectgarbage`][1] for more infos.
+//!
+//! [1]: https://www.lua.org/manual/5.1/manual.html#pdf-collectgarbage
+
+use std::ffi::c_int;
+
+use crate::ffi::{self, lua_State};
+
+/// Performs a full garbage collection cycle.
+pub fn collect() {
+    unsafe { gc(ffi::LUA_GCCOLLECT, 0) };
+}
+
+/// Performs an incremental step of garbage collection, where larger values of
+/// `size` mean larger steps. Returns `true` if the step finished a collection
+/// cycle.
+pub fn step(size: u32) -> bool {
+    unsafe { gc(ffi::LUA_GCSTEP, clamp(size)) == 1 }
+}
+
+/// Stops the garbage collector until [`restart`] is called.
+pub fn stop() {
+    unsafe { gc(ffi::LUA_GCSTOP, 0) };
+}
+
+/// Restarts the garbage collector after it's been stopped with [`stop`].
+pub fn restart() {
+    unsafe { gc(ffi::LUA_GCRESTART, 0) };
+}
+
+/// Returns `false` if the garbage collector has been stopped with [`stop`].
+pub fn is_running() -> bool {
+    unsafe { gc(ffi::LUA_GCISRUNNING, 0) != 0 }
+}
+
+/// Sets the collector's pause, i.e. how long it waits before starting a new
+/// cycle, as a percentage of the memory in use after the previous one.
+/// Returns the previous value.
+pub fn set_pause(pause: u32) -> u32 {
+    unsafe { gc(ffi::LUA_GCSETPAUSE, clamp(pause)) as u32 }
+}
+
+/// Sets the collector's step multiplier, i.e. its speed relative to memory
+/// allocation, as a percentage. Returns the previous value.
+pub fn set_step_mul(step_mul: u32) -> u32 {
+    unsafe { gc(ffi::LUA_GCSETSTEPMUL, clamp(step_mul)) as u32 }
+}
+
+/// Returns the total amount of memory in use by Lua, in bytes.
+pub fn memory_used() -> usize {
+    unsafe {
+        let kbytes = gc(ffi::LUA_GCCOUNT, 0) as usize;
+        let bytes = gc(ffi::LUA_GCCOUNTB, 0) as usize;
+        kbytes * 1024 + bytes
+    }
+}
+
+unsafe fn gc(what: c_int, data: c_int) -> c_int {
+    crate::with_state(|lstate: *mut lua_State| ffi::lua_gc(lstate, what, data))
+}
+
+#[inline]
+fn clamp(n: u32) -> c_int {
+    n.min(c_int::MAX as u32) as c_int
+}
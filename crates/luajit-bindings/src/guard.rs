@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+
+use crate::ffi::{self, lua_State};
+use crate::macros::count;
+use crate::{Error, RegistryRef};
+
+/// Lets Rust borrow strings owned by Lua without copying them.
+///
+/// Values popped through the guard are kept alive until the guard is dropped,
+/// so the slices borrowed from them can't outlive it.
+///
+/// ```ignore
+/// let guard = StackGuard::new(lstate);
+/// let text: &str = guard.pop()?;
+/// let bytes: Option<&[u8]> = guard.pop()?;
+/// ```
+pub struct StackGuard {
+    lstate: *mut lua_State,
+    anchors: RefCell<Vec<RegistryRef>>,
+}
+
+/// Trait implemented for types that borrow from a Lua value, and that can
+/// therefore only be popped through a [`StackGuard`].
+pub trait PoppableBorrowed<'a>: Sized {
+    /// Pops the value at the top of the stack, anchoring it in `guard` if
+    /// the returned value borrows from it.
+    unsafe fn pop_borrowed(guard: &'a StackGuard) -> Result<Self, Error>;
+}
+
+/// Trait implemented for the arguments of functions created with
+/// [`store_borrowed`](crate::function::store_borrowed).
+///
+/// The arguments are written with `'static` lifetimes, e.g.
+/// `(&'static str, Option<&'static [u8]>)`, and `Args` is the same type
+/// borrowing from a [`StackGuard`] instead, which is what the function
+/// receives.
+pub trait BorrowedArgs<'a> {
+    type Args: PoppableBorrowed<'a>;
+}
+
+impl StackGuard {
+    /// Creates a new guard for the given Lua state.
+    pub unsafe fn new(lstate: *mut lua_State) -> Self {
+        Self { lstate, anchors: RefCell::new(Vec::new()) }
+    }
+
+    /// Pops the value at the top of the stack.
+    pub unsafe fn pop<'a, T>(&'a self) -> Result<T, Error>
+    where
+        T: PoppableBorrowed<'a>,
+    {
+        T::pop_borrowed(self)
+    }
+
+    /// Returns the Lua state the guard was created for.
+    #[inline]
+    pub fn lstate(&self) -> *mut lua_State {
+        self.lstate
+    }
+
+    /// Pops the string at the top of the stack, returning a slice pointing
+    /// to its bytes that's valid for as long as the guard is alive.
+    unsafe fn pop_bytes<T>(&self) -> Result<&[u8], Error> {
+        let lstate = self.lstate;
+
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        match ffi::lua_type(lstate, -1) {
+            ffi::LUA_TSTRING | ffi::LUA_TNUMBER => {
+                let mut len = 0;
+                let ptr = ffi::lua_tolstring(lstate, -1, &mut len);
+
+                // Lua strings are immutable and never moved by the garbage
+                // collector, so the slice stays valid for as long as there's
+                // a reference to the string.
+                let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+                self.anchors
+                    .borrow_mut()
+                    .push(RegistryRef::from_stack(lstate));
+
+                Ok(bytes)
+            },
+
+            _ => {
+                Err(Error::pop_wrong_value::<T>(lstate, -1, ffi::LUA_TSTRING))
+            },
+        }
+    }
+}
+
+impl<'a> PoppableBorrowed<'a> for &'a [u8] {
+    unsafe fn pop_borrowed(guard: &'a StackGuard) -> Result<Self, Error> {
+        guard.pop_bytes::<Self>()
+    }
+}
+
+impl<'a> PoppableBorrowed<'a> for &'a str {
+    unsafe fn pop_borrowed(guard: &'a StackGuard) -> Result<Self, Error> {
+        std::str::from_utf8(guard.pop_bytes::<Self>()?)
+            .map_err(Error::pop_error_from_err::<Self, _>)
+    }
+}
+
+impl<'a, T> PoppableBorrowed<'a> for Option<T>
+where
+    T: PoppableBorrowed<'a>,
+{
+    unsafe fn pop_borrowed(guard: &'a StackGuard) -> Result<Self, Error> {
+        let lstate = guard.lstate;
+
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        match ffi::lua_type(lstate, -1) {
+            ffi::LUA_TNIL => {
+                ffi::lua_pop(lstate, 1);
+                Ok(None)
+            },
+            _ => T::pop_borrowed(guard).map(Some),
+        }
+    }
+}
+
+impl<'a> BorrowedArgs<'a> for &'static [u8] {
+    type Args = &'a [u8];
+}
+
+impl<'a> BorrowedArgs<'a> for &'static str {
+    type Args = &'a str;
+}
+
+impl<'a, T> BorrowedArgs<'a> for Option<T>
+where
+    T: BorrowedArgs<'a>,
+{
+    type Args = Option<T::Args>;
+}
+
+/// Implements `PoppableBorrowed` and `BorrowedArgs` for a tuple `(a, b, c,
+/// ..)` where all the elements in the tuple implement them.
+macro_rules! borrowed_tuple {
+    ($($name:ident)*) => (
+        impl<'a, $($name,)*> PoppableBorrowed<'a> for ($($name,)*)
+        where
+            $($name: PoppableBorrowed<'a>,)*
+        {
+            #[allow(non_snake_case)]
+            unsafe fn pop_borrowed(
+                guard: &'a StackGuard,
+            ) -> Result<Self, Error> {
+                // Missing arguments are `nil`s, like for owned tuples.
+                crate::utils::grow_stack(guard.lstate, count!($($name)*));
+                pop_borrowed_reverse!(guard, $($name)*);
+                Ok(($($name,)*))
+            }
+        }
+
+        impl<'a, $($name,)*> BorrowedArgs<'a> for ($($name,)*)
+        where
+            $($name: BorrowedArgs<'a>,)*
+        {
+            type Args = ($($name::Args,)*);
+        }
+    );
+}
+
+/// Pops the elements of a tuple starting from the last one.
+macro_rules! pop_borrowed_reverse {
+    ($guard:expr, $x:ident $($xs:ident)*) => {
+        pop_borrowed_reverse!($guard, $($xs)*);
+        let $x = $x::pop_borrowed($guard)?;
+    };
+
+    ($guard:expr,) => ();
+}
+
+borrowed_tuple!(A);
+borrowed_tuple!(A B);
+borrowed_tuple!(A B C);
+borrowed_tuple!(A B C D);
+borrowed_tuple!(A B C D E);
+borrowed_tuple!(A B C D E F);
+borrowed_tuple!(A B C D E F G);
+borrowed_tuple!(A B C D E F G H);
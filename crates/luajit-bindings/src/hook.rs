@@ -0,0 +1,236 @@
+//! Hooks called by the Lua interpreter while it's running code, see the
+//! documentation of [`lua_sethook`][1] for more infos.
+//!
+//! Only one hook can be set at a time, and setting a new one replaces the
+//! previous one. A hook can also replace or remove itself while it's running,
+//! in which case the change takes effect once it returns.
+//!
+//! [1]: https://www.lua.org/manual/5.1/manual.html#lua_sethook
+
+use std::cell::{Cell, RefCell};
+use std::ffi::c_int;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::ffi::{self, lua_Debug, lua_State};
+use crate::{utils, Error};
+
+/// How many instructions are executed between two checks of a [`Budget`].
+const BUDGET_STEP: u32 = 1000;
+
+/// The events that trigger a hook.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HookTriggers {
+    /// Call the hook when the interpreter calls a function.
+    pub on_calls: bool,
+
+    /// Call the hook when the interpreter returns from a function.
+    pub on_returns: bool,
+
+    /// Call the hook when the interpreter is about to start executing a new
+    /// line of code.
+    pub every_line: bool,
+
+    /// Call the hook after the interpreter executes every `n` instructions.
+    pub every_nth_instruction: Option<u32>,
+}
+
+/// The event that triggered a hook.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HookEvent {
+    /// A function is being called.
+    Call,
+
+    /// A function is returning, possibly from a tail call.
+    Return,
+
+    /// The interpreter is about to execute the given line.
+    Line(u32),
+
+    /// The interpreter has executed the number of instructions given in
+    /// [`HookTriggers::every_nth_instruction`].
+    Count,
+}
+
+/// A limit on how long a function can run for, used by
+/// [`function::call_with_budget`](crate::function::call_with_budget).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Budget {
+    /// The maximum number of Lua instructions to execute.
+    Instructions(u32),
+
+    /// The maximum amount of wall-clock time to run for.
+    Time(Duration),
+}
+
+type Callback =
+    Box<dyn FnMut(*mut lua_State, HookEvent) -> Result<(), Error> + 'static>;
+
+thread_local! {
+    static HOOK: RefCell<Option<Callback>> = RefCell::new(None);
+
+    /// Incremented every time the hook is set or removed, to tell whether a
+    /// running hook replaced itself.
+    static GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Sets a hook that's called with every event in `triggers`. If the hook
+/// returns an error it's raised as a Lua error from the code that triggered
+/// the event.
+pub fn set<F>(triggers: HookTriggers, mut hook: F)
+where
+    F: FnMut(HookEvent) -> Result<(), Error> + 'static,
+{
+    unsafe {
+        crate::with_state(|lstate| {
+            set_callback(lstate, triggers, Box::new(move |_, ev| hook(ev)))
+        })
+    }
+}
+
+/// Removes the hook set with [`set`], if any.
+pub fn remove() {
+    unsafe {
+        crate::with_state(|lstate| {
+            ffi::lua_sethook(lstate, None, 0, 0);
+            replace_callback(None);
+        })
+    }
+}
+
+unsafe fn set_callback(
+    lstate: *mut lua_State,
+    triggers: HookTriggers,
+    callback: Callback,
+) {
+    let mut mask = 0;
+
+    if triggers.on_calls {
+        mask |= ffi::LUA_MASKCALL;
+    }
+
+    if triggers.on_returns {
+        mask |= ffi::LUA_MASKRET;
+    }
+
+    if triggers.every_line {
+        mask |= ffi::LUA_MASKLINE;
+    }
+
+    let count = match triggers.every_nth_instruction {
+        Some(n) => {
+            mask |= ffi::LUA_MASKCOUNT;
+            n.clamp(1, c_int::MAX as u32) as c_int
+        },
+        None => 0,
+    };
+
+    replace_callback(Some(callback));
+    ffi::lua_sethook(lstate, Some(c_hook), mask, count);
+}
+
+/// Replaces the current callback, telling a running hook not to restore it.
+fn replace_callback(callback: Option<Callback>) {
+    GENERATION.with(|generation| generation.set(generation.get() + 1));
+    HOOK.with(|hook| hook.replace(callback));
+}
+
+/// Calls `fun` with a hook raising an error once the budget is exceeded,
+/// restoring the previous hook afterwards.
+///
+/// Once the budget is exceeded the error is raised again after every
+/// instruction, so that the code can't keep running by catching it with
+/// `pcall`.
+pub(crate) unsafe fn with_budget<F, R>(
+    lstate: *mut lua_State,
+    budget: Budget,
+    fun: F,
+) -> Result<R, Error>
+where
+    F: FnOnce() -> Result<R, Error>,
+{
+    let prev_hook = ffi::lua_gethook(lstate);
+    let prev_mask = ffi::lua_gethookmask(lstate);
+    let prev_count = ffi::lua_gethookcount(lstate);
+    let prev_callback = HOOK.with(|hook| hook.take());
+
+    let (step, mut is_exceeded): (_, Box<dyn FnMut(u64) -> bool>) =
+        match budget {
+            Budget::Instructions(max) => {
+                (max.clamp(1, BUDGET_STEP), Box::new(move |n| n > max as u64))
+            },
+            Budget::Time(max) => {
+                let start = Instant::now();
+                (BUDGET_STEP, Box::new(move |_| start.elapsed() > max))
+            },
+        };
+
+    let exceeded = Rc::new(Cell::new(false));
+    let mut executed = 0u64;
+
+    let callback = {
+        let exceeded = Rc::clone(&exceeded);
+        move |lstate, _| {
+            if !exceeded.get() {
+                executed += step as u64;
+                if !is_exceeded(executed) {
+                    return Ok(());
+                }
+                exceeded.set(true);
+                ffi::lua_sethook(lstate, Some(c_hook), ffi::LUA_MASKCOUNT, 1);
+            }
+            Err(Error::Timeout)
+        }
+    };
+
+    let triggers = HookTriggers {
+        every_nth_instruction: Some(step),
+        ..Default::default()
+    };
+
+    set_callback(lstate, triggers, Box::new(callback));
+
+    let res = fun();
+
+    ffi::lua_sethook(lstate, prev_hook, prev_mask, prev_count);
+    HOOK.with(|hook| hook.replace(prev_callback));
+
+    if exceeded.get() {
+        Err(Error::Timeout)
+    } else {
+        res
+    }
+}
+
+unsafe extern "C" fn c_hook(lstate: *mut lua_State, ar: *mut lua_Debug) {
+    let event = match (*ar).event {
+        ffi::LUA_HOOKCALL => HookEvent::Call,
+        ffi::LUA_HOOKRET | ffi::LUA_HOOKTAILRET => HookEvent::Return,
+        ffi::LUA_HOOKLINE => HookEvent::Line((*ar).currentline as u32),
+        _ => HookEvent::Count,
+    };
+
+    // Take the callback out while it runs so that it can call `set` or
+    // `remove` without finding the `RefCell` already borrowed.
+    let mut callback = match HOOK.with(|hook| hook.take()) {
+        Some(callback) => callback,
+        None => return,
+    };
+
+    let generation = GENERATION.with(Cell::get);
+
+    let res =
+        utils::catch_panic(|| callback(lstate, event)).and_then(|res| res);
+
+    // Put it back unless it replaced or removed itself. This has to happen
+    // before raising the error, which doesn't return.
+    if GENERATION.with(Cell::get) == generation {
+        HOOK.with(|hook| hook.replace(Some(callback)));
+    } else {
+        drop(callback);
+    }
+
+    if let Err(err) = res {
+        utils::handle_error(lstate, &err);
+    }
+}
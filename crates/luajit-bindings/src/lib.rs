@@ -1,16 +1,42 @@
 #![allow(clippy::missing_safety_doc)]
+mod coroutine;
 mod error;
 pub mod ffi;
 pub mod function;
+pub mod gc;
+mod guard;
+pub mod hook;
+mod light_userdata;
+mod load;
+mod lookup;
 pub mod macros;
+mod path;
 mod poppable;
 mod pushable;
+mod registry;
 mod state;
+mod table;
+#[cfg(feature = "test-stubs")]
+mod test_stubs;
+mod userdata;
 pub mod utils;
+mod variadic;
 
+pub use coroutine::{Coroutine, CoroutineStatus, Yielding};
 pub use error::Error;
+pub use guard::{BorrowedArgs, PoppableBorrowed, StackGuard};
+pub use light_userdata::LightUserData;
+pub use load::{eval, load_chunk};
+pub use lookup::{get_path, require};
 #[doc(hidden)]
 pub use macros::__print;
+pub use path::{Path, PathSegment};
 pub use poppable::Poppable;
 pub use pushable::Pushable;
+#[doc(hidden)]
+pub use pushable::{push_one, push_or_restore};
+pub use registry::RegistryRef;
 pub use state::{init, with_state};
+pub use table::{Ipairs, Pairs, Table};
+pub use userdata::{MetaMethod, UserData, UserDataMethods, UserDataRef};
+pub use variadic::Variadic;
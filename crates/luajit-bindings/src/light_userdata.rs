@@ -0,0 +1,128 @@
+use std::ffi::{c_int, c_void};
+
+use crate::ffi::{self, lua_State};
+use crate::macros::cstr;
+use crate::{Error, Poppable, Pushable};
+
+/// A raw pointer passed to and from Lua as a light userdata.
+///
+/// Besides light userdata, it can also be popped from LuaJIT's cdata pointers
+/// and arrays, e.g. from a buffer created with `ffi.new`, which lets Lua and
+/// Rust share memory without copying it. Lua code can turn the pointer back
+/// into cdata with `ffi.cast`.
+///
+/// Note that popping a pointer doesn't keep the memory it points to alive,
+/// so cdata allocated by Lua must be kept referenced for as long as Rust
+/// uses it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LightUserData(pub *mut c_void);
+
+impl LightUserData {
+    /// Returns the raw pointer.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+impl From<*mut c_void> for LightUserData {
+    #[inline]
+    fn from(ptr: *mut c_void) -> Self {
+        Self(ptr)
+    }
+}
+
+impl Poppable for LightUserData {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        match ffi::lua_type(lstate, -1) {
+            ffi::LUA_TLIGHTUSERDATA => {
+                let ptr = ffi::lua_touserdata(lstate, -1);
+                ffi::lua_pop(lstate, 1);
+                Ok(Self(ptr))
+            },
+
+            ffi::LUA_TCDATA => {
+                let ptr = cdata_ptr(lstate).map_err(|err| {
+                    Error::pop_error(std::any::type_name::<Self>(), err)
+                });
+                ffi::lua_pop(lstate, 1);
+                ptr.map(Self)
+            },
+
+            _ => Err(Error::pop_wrong_value::<Self>(
+                lstate,
+                -1,
+                ffi::LUA_TLIGHTUSERDATA,
+            )),
+        }
+    }
+}
+
+impl Pushable for LightUserData {
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        ffi::lua_pushlightuserdata(lstate, self.0);
+        Ok(1)
+    }
+}
+
+/// Returns the address pointed to by the cdata at the top of the stack,
+/// without popping it.
+///
+/// The C API can only give us the address of the cdata's payload, which for
+/// pointers is where the pointer is stored and for arrays is where the
+/// elements are. To handle both the same way the value is first converted to
+/// a `void *` with `ffi.cast`, and the pointer is read from its payload.
+unsafe fn cdata_ptr(lstate: *mut lua_State) -> Result<*mut c_void, String> {
+    let top = ffi::lua_gettop(lstate);
+    let res = cast_to_ptr(lstate, top);
+    ffi::lua_settop(lstate, top);
+    res
+}
+
+/// Casts the cdata at the absolute index `idx` to a `void *`, leaving the
+/// intermediate values on the stack.
+unsafe fn cast_to_ptr(
+    lstate: *mut lua_State,
+    idx: c_int,
+) -> Result<*mut c_void, String> {
+    // `ffi.cast` happily turns numbers like `1LL` into pointers, so we only
+    // accept the cdata that `tonumber` can't convert, i.e. pointers, arrays
+    // and references.
+    ffi::lua_getglobal(lstate, cstr!("tonumber"));
+    ffi::lua_pushvalue(lstate, idx);
+    pcall_one(lstate, 1)?;
+
+    if ffi::lua_type(lstate, -1) != ffi::LUA_TNIL {
+        return Err("expected a pointer, array or reference cdata, got a \
+                    number"
+            .into());
+    }
+
+    ffi::lua_getglobal(lstate, cstr!("require"));
+    ffi::lua_pushstring(lstate, cstr!("ffi"));
+    pcall_one(lstate, 1)?;
+
+    ffi::lua_getfield(lstate, -1, cstr!("cast"));
+    ffi::lua_pushstring(lstate, cstr!("void *"));
+    ffi::lua_pushvalue(lstate, idx);
+    pcall_one(lstate, 2)?;
+
+    let payload = ffi::lua_topointer(lstate, -1);
+    Ok(*(payload as *const *mut c_void))
+}
+
+/// Calls a function with the `nargs` values above it, leaving its only return
+/// value on the stack.
+unsafe fn pcall_one(
+    lstate: *mut lua_State,
+    nargs: c_int,
+) -> Result<(), String> {
+    match ffi::lua_pcall(lstate, nargs, 1, 0) {
+        ffi::LUA_OK => Ok(()),
+        _ => Err(crate::function::error_message(lstate)),
+    }
+}
@@ -0,0 +1,61 @@
+use std::ffi::CString;
+
+use crate::ffi::{self, lua_State};
+use crate::function::{self, error_message};
+use crate::{Error, Poppable};
+
+/// Loads `chunk` as a Lua function and pushes it on the stack without running
+/// it.
+///
+/// `name` is used as the chunk name in error messages and tracebacks. Like
+/// Lua's `load`, names starting with `=` are shown as they are, e.g.
+/// `"=my_plugin"`.
+pub unsafe fn load_chunk(
+    lstate: *mut lua_State,
+    chunk: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let name =
+        CString::new(name).map_err(Error::push_error_from_err::<&str, _>)?;
+
+    match ffi::luaL_loadbuffer(
+        lstate,
+        chunk.as_ptr() as *const _,
+        chunk.len(),
+        name.as_ptr(),
+    ) {
+        ffi::LUA_OK => Ok(()),
+
+        err_code => {
+            let msg = error_message(lstate);
+            ffi::lua_pop(lstate, 1);
+
+            match err_code {
+                ffi::LUA_ERRSYNTAX => Err(Error::SyntaxError(msg)),
+                ffi::LUA_ERRMEM => Err(Error::MemoryError(msg)),
+                _ => unreachable!(),
+            }
+        },
+    }
+}
+
+/// Evaluates a chunk of Lua code, returning its results.
+///
+/// Like in the Lua REPL, the chunk can either be an expression or a sequence
+/// of statements, so both `eval::<i32>("1 + 2")` and
+/// `eval::<i32>("local a = 1; return a + 2")` return `3`.
+pub fn eval<R>(chunk: &str) -> Result<R, Error>
+where
+    R: Poppable,
+{
+    const NAME: &str = "=(eval)";
+
+    unsafe {
+        crate::with_state(|lstate| {
+            if load_chunk(lstate, &format!("return {chunk}"), NAME).is_err() {
+                load_chunk(lstate, chunk, NAME)?;
+            }
+            function::pcall(lstate, (), false)
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use std::ffi::c_int;
+
+use crate::ffi::{self, lua_State};
+use crate::macros::cstr;
+use crate::{function, utils, Error, Poppable};
+
+/// Calls Lua's `require` to load a module, returning its value.
+pub fn require<T>(module: &str) -> Result<T, Error>
+where
+    T: Poppable,
+{
+    unsafe {
+        crate::with_state(|lstate| {
+            ffi::lua_getglobal(lstate, cstr!("require"));
+            function::pcall(lstate, module, false)
+        })
+    }
+}
+
+/// Returns the value at a dotted path starting from the global environment,
+/// e.g. `get_path::<Function<(), ()>>("vim.lsp.buf.hover")`.
+///
+/// Fields are looked up like in Lua, so metamethods are honored and lazily
+/// loaded modules like `vim.lsp` are loaded on first access. If a segment of
+/// the path can't be indexed the error reports which one it was.
+pub fn get_path<T>(path: &str) -> Result<T, Error>
+where
+    T: Poppable,
+{
+    unsafe {
+        crate::with_state(|lstate| {
+            let top = ffi::lua_gettop(lstate);
+            let res = push_path::<T>(lstate, path).and_then(|_| {
+                let fields = path.split('.').collect::<Vec<_>>();
+                T::pop(lstate).map_err(|err| in_path(err, &fields))
+            });
+            ffi::lua_settop(lstate, top);
+            res
+        })
+    }
+}
+
+/// Pushes the value at `path` on the stack. `T` is only used in error
+/// messages.
+unsafe fn push_path<T>(
+    lstate: *mut lua_State,
+    path: &str,
+) -> Result<(), Error> {
+    ffi::lua_pushvalue(lstate, ffi::LUA_GLOBALSINDEX);
+
+    let fields = path.split('.').collect::<Vec<_>>();
+
+    for (i, field) in fields.iter().enumerate() {
+        let ty = ffi::lua_type(lstate, -1);
+
+        if ty != ffi::LUA_TTABLE && !has_metatable(lstate) {
+            let err = Error::pop_error(
+                std::any::type_name::<T>(),
+                format!(
+                    "can't index a {} value with `{field}`",
+                    utils::type_name(ty)
+                ),
+            );
+            return Err(in_path(err, &fields[..i]));
+        }
+
+        ffi::lua_pushcfunction(lstate, index);
+        ffi::lua_insert(lstate, -2);
+        ffi::lua_pushlstring(lstate, field.as_ptr() as *const _, field.len());
+
+        match ffi::lua_pcall(lstate, 2, 1, 0) {
+            ffi::LUA_OK => {},
+            err_code => return Err(function::pop_error(lstate, err_code)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Indexes the first argument with the second one, possibly triggering the
+/// `__index` metamethod.
+unsafe extern "C" fn index(lstate: *mut lua_State) -> c_int {
+    ffi::lua_gettable(lstate, 1);
+    1
+}
+
+/// Whether the value at the top of the stack has a metatable.
+unsafe fn has_metatable(lstate: *mut lua_State) -> bool {
+    if ffi::lua_getmetatable(lstate, -1) == 0 {
+        return false;
+    }
+    ffi::lua_pop(lstate, 1);
+    true
+}
+
+/// Adds the path leading to the value that caused the error.
+fn in_path(err: Error, fields: &[&str]) -> Error {
+    fields.iter().rev().fold(err, |err, field| err.in_field(*field))
+}
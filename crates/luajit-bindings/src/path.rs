@@ -0,0 +1,134 @@
+use std::ffi::c_int;
+use std::fmt;
+
+use crate::ffi::{self, lua_State};
+
+/// The path leading to a value nested inside other tables, e.g.
+/// `keymaps[3].mode`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+/// A single step of a [`Path`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PathSegment {
+    /// A string key, displayed as `.foo` or `["foo bar"]`.
+    Field(String),
+
+    /// An integer key, displayed as `[3]`.
+    Index(i64),
+
+    /// Any other key, displayed as `[true]` or `[{...}]`.
+    Other(String),
+}
+
+impl Path {
+    /// Returns `true` if the path doesn't contain any segments, i.e. if it
+    /// points to the outermost value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns an iterator over the segments of the path, starting from the
+    /// outermost one.
+    #[inline]
+    pub fn segments(&self) -> impl Iterator<Item = &PathSegment> + '_ {
+        self.segments.iter()
+    }
+
+    /// Adds a segment at the start of the path. Paths are built from the
+    /// inside out as errors bubble up through the nested values.
+    #[inline]
+    pub fn prepend(&mut self, segment: PathSegment) {
+        self.segments.insert(0, segment);
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Field(field) if is_identifier(field) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(field)?;
+                },
+                PathSegment::Field(field) => write!(f, "[{field:?}]")?,
+                PathSegment::Index(idx) => write!(f, "[{idx}]")?,
+                PathSegment::Other(key) => write!(f, "[{key}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PathSegment {
+    /// Creates a segment from the table key at the given stack index.
+    pub unsafe fn from_key(lstate: *mut lua_State, idx: c_int) -> Self {
+        match ffi::lua_type(lstate, idx) {
+            ffi::LUA_TSTRING => {
+                let mut len = 0;
+                let ptr = ffi::lua_tolstring(lstate, idx, &mut len);
+                let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+                Self::Field(String::from_utf8_lossy(bytes).into_owned())
+            },
+
+            ffi::LUA_TNUMBER => {
+                let n = ffi::lua_tonumber(lstate, idx);
+                if n == (n as i64) as ffi::lua_Number {
+                    Self::Index(n as i64)
+                } else {
+                    Self::Other(n.to_string())
+                }
+            },
+
+            _ => Self::Other(crate::utils::inspect_with_depth(lstate, idx, 0)),
+        }
+    }
+}
+
+/// Whether `s` can be used as a field name in Lua without quoting it.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: Vec<PathSegment>) -> String {
+        Path { segments }.to_string()
+    }
+
+    #[test]
+    fn display_path() {
+        use PathSegment::*;
+
+        assert_eq!("", path(vec![]));
+        assert_eq!("[1]", path(vec![Index(1)]));
+
+        assert_eq!(
+            "opts.keymaps[3].mode",
+            path(vec![
+                Field("opts".into()),
+                Field("keymaps".into()),
+                Index(3),
+                Field("mode".into()),
+            ])
+        );
+
+        assert_eq!(
+            "foo[\"bar baz\"][{...}]",
+            path(vec![
+                Field("foo".into()),
+                Field("bar baz".into()),
+                Other("{...}".into()),
+            ])
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::c_int;
 use std::hash::Hash;
 
 use crate::ffi::*;
@@ -9,6 +11,32 @@ use crate::Error;
 pub trait Poppable: Sized {
     /// Pops the value at the top of the stack.
     unsafe fn pop(lua_state: *mut lua_State) -> Result<Self, Error>;
+
+    /// Pops the value at the top of the stack knowing that the `reserved`
+    /// values at the bottom of the stack belong to other values. If the stack
+    /// has less than `reserved + 1` values it's padded with `nil`s.
+    ///
+    /// This is used when popping tuples, and only needs to be overridden by
+    /// types like [`Variadic`](crate::Variadic) which pop all the remaining
+    /// values.
+    #[doc(hidden)]
+    unsafe fn pop_reserving(
+        lua_state: *mut lua_State,
+        reserved: c_int,
+    ) -> Result<Self, Error> {
+        crate::utils::grow_stack(lua_state, reserved + 1);
+        Self::pop(lua_state)
+    }
+
+    /// Builds a `Vec<Self>` from the bytes of a Lua string, if possible.
+    ///
+    /// This lets `Vec<u8>` be popped from a string without going through a
+    /// table or validating it as UTF-8. Every other type uses the default
+    /// implementation, which returns `None`.
+    #[doc(hidden)]
+    fn vec_from_bytes(_bytes: &[u8]) -> Option<Vec<Self>> {
+        None
+    }
 }
 
 impl Poppable for () {
@@ -22,7 +50,7 @@ impl Poppable for () {
                 lua_pop(state, 1);
                 Ok(())
             },
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TNIL, other)),
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TNIL)),
         }
     }
 }
@@ -39,7 +67,7 @@ impl Poppable for bool {
                 lua_pop(state, 1);
                 Ok(b)
             },
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TBOOLEAN, other)),
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TBOOLEAN)),
         }
     }
 }
@@ -56,7 +84,7 @@ impl Poppable for lua_Integer {
                 lua_pop(state, 1);
                 Ok(n)
             },
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TNUMBER, other)),
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TNUMBER)),
         }
     }
 }
@@ -78,7 +106,6 @@ macro_rules! pop_try_from_integer {
 }
 
 pop_try_from_integer!(i8);
-pop_try_from_integer!(u8);
 pop_try_from_integer!(i16);
 pop_try_from_integer!(u16);
 pop_try_from_integer!(i32);
@@ -87,6 +114,19 @@ pop_try_from_integer!(i64);
 pop_try_from_integer!(u64);
 pop_try_from_integer!(usize);
 
+impl Poppable for u8 {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, crate::Error> {
+        lua_Integer::pop(lstate)?
+            .try_into()
+            .map_err(Error::pop_error_from_err::<Self, _>)
+    }
+
+    #[inline]
+    fn vec_from_bytes(bytes: &[u8]) -> Option<Vec<Self>> {
+        Some(bytes.to_owned())
+    }
+}
+
 impl Poppable for lua_Number {
     unsafe fn pop(state: *mut lua_State) -> Result<Self, crate::Error> {
         if lua_gettop(state) == 0 {
@@ -99,7 +139,7 @@ impl Poppable for lua_Number {
                 lua_pop(state, 1);
                 Ok(n)
             },
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TNUMBER, other)),
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TNUMBER)),
         }
     }
 }
@@ -132,7 +172,7 @@ impl Poppable for String {
 
                 Ok(str)
             },
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TSTRING, other)),
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TSTRING)),
         }
     }
 }
@@ -175,7 +215,11 @@ where
                 lua_pushnil(state);
 
                 while lua_next(state, -2) != 0 {
-                    vec.push(T::pop(state)?);
+                    let key_idx = lua_gettop(state) - 1;
+                    vec.push(
+                        T::pop(state)
+                            .map_err(|err| err.at_key(state, key_idx))?,
+                    );
                 }
 
                 // Pop the table.
@@ -184,51 +228,210 @@ where
                 Ok(vec)
             },
 
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TTABLE, other)),
+            LUA_TSTRING => {
+                let mut len = 0;
+                let ptr = lua_tolstring(state, -1, &mut len);
+                let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+
+                match T::vec_from_bytes(bytes) {
+                    Some(vec) => {
+                        lua_pop(state, 1);
+                        Ok(vec)
+                    },
+                    None => Err(Error::pop_wrong_value::<Self>(
+                        state, -1, LUA_TTABLE,
+                    )),
+                }
+            },
+
+            _ => Err(Error::pop_wrong_value::<Self>(state, -1, LUA_TTABLE)),
         }
     }
 }
 
+impl<T, const N: usize> Poppable for [T; N]
+where
+    T: Poppable,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        <Vec<T> as Poppable>::pop(state)?.try_into().map_err(|vec: Vec<T>| {
+            Error::pop_error(
+                std::any::type_name::<Self>(),
+                format!("expected {N} elements, found {}", vec.len()),
+            )
+        })
+    }
+}
+
+impl<T> Poppable for Box<T>
+where
+    T: Poppable,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        T::pop(state).map(Box::new)
+    }
+}
+
+impl<T> Poppable for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: Poppable,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        T::Owned::pop(state).map(Cow::Owned)
+    }
+}
+
+/// Pops either a single value or the `nil, err` pair returned by Lua
+/// functions that can fail. A `nil` followed by another value is always
+/// popped as an error.
+impl<T, E> Poppable for Result<T, E>
+where
+    T: Poppable,
+    E: Poppable,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        Self::pop_reserving(state, 0)
+    }
+
+    unsafe fn pop_reserving(
+        state: *mut lua_State,
+        reserved: c_int,
+    ) -> Result<Self, Error> {
+        if lua_gettop(state) - reserved >= 2 && lua_type(state, -2) == LUA_TNIL
+        {
+            let err = E::pop(state)?;
+            // Pop the `nil`.
+            lua_pop(state, 1);
+            return Ok(Err(err));
+        }
+
+        T::pop_reserving(state, reserved).map(Ok)
+    }
+}
+
 impl<K, V> Poppable for HashMap<K, V>
 where
     K: Poppable + Eq + Hash,
     V: Poppable,
 {
     unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
-        if lua_gettop(state) == 0 {
-            return Err(Error::PopEmptyStack);
-        }
+        let mut map = HashMap::new();
+        pop_pairs::<Self, _, _>(state, |key, value| {
+            map.insert(key, value);
+        })?;
+        Ok(map)
+    }
+}
 
-        match lua_type(state, -1) {
-            LUA_TTABLE => {
-                // TODO: check that the table is an dictionary-like table and
-                // not an array-like one.
+impl<K, V> Poppable for BTreeMap<K, V>
+where
+    K: Poppable + Ord,
+    V: Poppable,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        let mut map = BTreeMap::new();
+        pop_pairs::<Self, _, _>(state, |key, value| {
+            map.insert(key, value);
+        })?;
+        Ok(map)
+    }
+}
 
-                let mut map = HashMap::with_capacity(lua_objlen(state, -1));
+/// Pops a set from a table like `{ foo = true, bar = true }`. Keys whose
+/// value is `false` are skipped.
+impl<T> Poppable for HashSet<T>
+where
+    T: Poppable + Eq + Hash,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        let mut set = HashSet::new();
+        pop_pairs::<Self, _, _>(state, |key, Truthy(is_member)| {
+            if is_member {
+                set.insert(key);
+            }
+        })?;
+        Ok(set)
+    }
+}
 
-                lua_pushnil(state);
+/// Pops a set from a table like `{ foo = true, bar = true }`. Keys whose
+/// value is `false` are skipped.
+impl<T> Poppable for BTreeSet<T>
+where
+    T: Poppable + Ord,
+{
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        let mut set = BTreeSet::new();
+        pop_pairs::<Self, _, _>(state, |key, Truthy(is_member)| {
+            if is_member {
+                set.insert(key);
+            }
+        })?;
+        Ok(set)
+    }
+}
 
-                while lua_next(state, -2) != 0 {
-                    let value = V::pop(state)?;
+/// Pops the table at the top of the stack, passing each of its key-value
+/// pairs to `insert`. `T` is the type being popped, and it's only used in
+/// error messages.
+unsafe fn pop_pairs<T, K, V>(
+    state: *mut lua_State,
+    mut insert: impl FnMut(K, V),
+) -> Result<(), Error>
+where
+    K: Poppable,
+    V: Poppable,
+{
+    if lua_gettop(state) == 0 {
+        return Err(Error::PopEmptyStack);
+    }
 
-                    // NOTE: the following `K::pop` will pop the key, so we
-                    // push another copy of the key on the stack for the next
-                    // iteration.
-                    lua_pushvalue(state, -1);
+    match lua_type(state, -1) {
+        LUA_TTABLE => {
+            // TODO: check that the table is an dictionary-like table and
+            // not an array-like one.
 
-                    let key = K::pop(state)?;
+            lua_pushnil(state);
 
-                    map.insert(key, value);
-                }
+            while lua_next(state, -2) != 0 {
+                let key_idx = lua_gettop(state) - 1;
+                let value =
+                    V::pop(state).map_err(|err| err.at_key(state, key_idx))?;
 
-                // Pop the table.
-                lua_pop(state, 1);
+                // NOTE: the following `K::pop` will pop the key, so we
+                // push another copy of the key on the stack for the next
+                // iteration.
+                lua_pushvalue(state, -1);
 
-                Ok(map)
-            },
+                let key = K::pop(state)?;
+
+                insert(key, value);
+            }
+
+            // Pop the table.
+            lua_pop(state, 1);
+
+            Ok(())
+        },
+
+        _ => Err(Error::pop_wrong_value::<T>(state, -1, LUA_TTABLE)),
+    }
+}
+
+/// Whether a value is truthy, i.e. neither `nil` nor `false`. Used for the
+/// values of tables popped into sets.
+struct Truthy(bool);
 
-            other => Err(Error::pop_wrong_type::<Self>(LUA_TTABLE, other)),
+impl Poppable for Truthy {
+    unsafe fn pop(state: *mut lua_State) -> Result<Self, Error> {
+        if lua_gettop(state) == 0 {
+            return Err(Error::PopEmptyStack);
         }
+
+        let is_truthy = lua_toboolean(state, -1) != 0;
+        lua_pop(state, 1);
+        Ok(Self(is_truthy))
     }
 }
 
@@ -240,23 +443,32 @@ macro_rules! pop_tuple {
         where
             $($name: Poppable,)*
         {
-            #[allow(non_snake_case)]
             unsafe fn pop(state: *mut lua_State) -> Result<Self, crate::Error> {
-                crate::utils::grow_stack(state, count!($($name)*));
-                pop_reverse!(state, $($name)*);
+                Self::pop_reserving(state, 0)
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn pop_reserving(
+                state: *mut lua_State,
+                reserved: c_int,
+            ) -> Result<Self, crate::Error> {
+                pop_reverse!(state, reserved, [] $($name)*);
                 Ok(($($name,)*))
             }
         }
     );
 }
 
+/// Pops the elements of a tuple starting from the last one. Every element
+/// knows how many values below it belong to the elements before it or are
+/// reserved by the caller, which is needed by [`Variadic`](crate::Variadic).
 macro_rules! pop_reverse {
-    ($lua_state:expr, $x:ident $($xs:ident)*) => {
-        pop_reverse!($lua_state, $($xs)*);
-        let $x = $x::pop($lua_state)?;
+    ($lua_state:expr, $reserved:expr, [$($prev:ident)*] $x:ident $($xs:ident)*) => {
+        pop_reverse!($lua_state, $reserved, [$($prev)* $x] $($xs)*);
+        let $x = $x::pop_reserving($lua_state, $reserved + count!($($prev)*))?;
     };
 
-    ($lstate:expr,) => ();
+    ($lstate:expr, $reserved:expr, [$($prev:ident)*]) => ();
 }
 
 pop_tuple!(A);
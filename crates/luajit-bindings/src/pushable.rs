@@ -1,9 +1,16 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ffi::{c_char, c_int};
 
 use crate::ffi::{self, lua_Integer, lua_Number, lua_State};
-use crate::macros::count;
 
 /// Trait implemented for types that can be pushed onto the Lua stack.
+///
+/// Byte containers, i.e. `Vec<u8>`, `[u8; N]` and `&[u8]`, are pushed as Lua
+/// strings without checking that they're valid UTF-8. Note that this is a
+/// breaking change from previous releases, which pushed `Vec<u8>`s as arrays
+/// of integers: to keep that behaviour, convert the bytes to a wider integer
+/// type first, e.g. `bytes.into_iter().map(u32::from).collect::<Vec<_>>()`.
 pub trait Pushable {
     /// Pushes all its values on the Lua stack, returning the number of values
     /// that it pushed.
@@ -11,15 +18,80 @@ pub trait Pushable {
         self,
         lstate: *mut lua_State,
     ) -> Result<c_int, crate::Error>;
+
+    /// Pushes a `Vec<Self>`. By default vectors are pushed as array-like
+    /// tables, but `Vec<u8>` overrides this to push a Lua string instead.
+    #[doc(hidden)]
+    unsafe fn push_vec(
+        vec: Vec<Self>,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error>
+    where
+        Self: Sized,
+    {
+        push_array(vec, lstate)
+    }
+
+    /// Pushes a `[Self; N]`, which like [`push_vec`](Pushable::push_vec) is
+    /// an array-like table by default and a Lua string for `[u8; N]`.
+    #[doc(hidden)]
+    unsafe fn push_array<const N: usize>(
+        array: [Self; N],
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error>
+    where
+        Self: Sized,
+    {
+        push_array(array, lstate)
+    }
 }
 
+/// Pushes a value that has to take up exactly one slot on the stack, like a
+/// table field. Values pushing zero or multiple values (e.g. `()`) are popped
+/// again and an error is returned.
+#[doc(hidden)]
+pub unsafe fn push_one<T: Pushable>(
+    value: T,
+    lstate: *mut lua_State,
+) -> Result<(), crate::Error> {
+    match value.push(lstate)? {
+        1 => Ok(()),
+        n => {
+            ffi::lua_pop(lstate, n);
+            Err(crate::Error::push_error(
+                std::any::type_name::<T>(),
+                format!("expected a single value, but {n} were pushed"),
+            ))
+        },
+    }
+}
+
+/// Runs `push`, truncating the stack back to its previous height if it fails
+/// so that a partially built table isn't left on the stack.
+#[doc(hidden)]
+pub unsafe fn push_or_restore<F>(
+    lstate: *mut lua_State,
+    push: F,
+) -> Result<c_int, crate::Error>
+where
+    F: FnOnce() -> Result<c_int, crate::Error>,
+{
+    let top = ffi::lua_gettop(lstate);
+    let res = push();
+    if res.is_err() {
+        ffi::lua_settop(lstate, top);
+    }
+    res
+}
+
+/// Pushes zero values, so that Rust functions returning `()` don't return
+/// anything to Lua.
 impl Pushable for () {
     unsafe fn push(
         self,
-        lstate: *mut lua_State,
+        _lstate: *mut lua_State,
     ) -> Result<c_int, crate::Error> {
-        ffi::lua_pushnil(lstate);
-        Ok(1)
+        Ok(0)
     }
 }
 
@@ -83,7 +155,6 @@ macro_rules! push_try_into_integer {
 }
 
 push_into_integer!(i8);
-push_into_integer!(u8);
 push_into_integer!(i16);
 push_try_into_integer!(u16);
 push_try_into_integer!(i32);
@@ -92,6 +163,29 @@ push_try_into_integer!(i64);
 push_try_into_integer!(u64);
 push_try_into_integer!(usize);
 
+impl Pushable for u8 {
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        (self as lua_Integer).push(lstate)
+    }
+
+    unsafe fn push_vec(
+        vec: Vec<Self>,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        vec.as_slice().push(lstate)
+    }
+
+    unsafe fn push_array<const N: usize>(
+        array: [Self; N],
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        array.as_slice().push(lstate)
+    }
+}
+
 impl Pushable for lua_Number {
     unsafe fn push(
         self,
@@ -125,6 +219,36 @@ impl Pushable for String {
     }
 }
 
+impl Pushable for &str {
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        ffi::lua_pushlstring(
+            lstate,
+            self.as_ptr() as *const c_char,
+            self.len(),
+        );
+        Ok(1)
+    }
+}
+
+/// Pushes the bytes as a Lua string, without checking that they're valid
+/// UTF-8.
+impl Pushable for &[u8] {
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        ffi::lua_pushlstring(
+            lstate,
+            self.as_ptr() as *const c_char,
+            self.len(),
+        );
+        Ok(1)
+    }
+}
+
 impl<T> Pushable for Option<T>
 where
     T: Pushable,
@@ -135,7 +259,10 @@ where
     ) -> Result<c_int, crate::Error> {
         match self {
             Some(t) => t.push(lstate),
-            None => ().push(lstate),
+            None => {
+                ffi::lua_pushnil(lstate);
+                Ok(1)
+            },
         }
     }
 }
@@ -148,15 +275,165 @@ where
         self,
         lstate: *mut lua_State,
     ) -> Result<c_int, crate::Error> {
-        ffi::lua_createtable(lstate, self.len() as _, 0);
+        T::push_vec(self, lstate)
+    }
+}
 
-        for (i, obj) in self.into_iter().enumerate() {
-            obj.push(lstate)?;
+impl<T, const N: usize> Pushable for [T; N]
+where
+    T: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        T::push_array(self, lstate)
+    }
+}
+
+impl<T> Pushable for Box<T>
+where
+    T: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        (*self).push(lstate)
+    }
+}
+
+impl<T> Pushable for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        self.into_owned().push(lstate)
+    }
+}
+
+/// Pushes `Ok` values as they are and `Err`s as `nil, err`, the same
+/// convention used by Lua functions that can fail.
+impl<T, E> Pushable for Result<T, E>
+where
+    T: Pushable,
+    E: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        match self {
+            Ok(t) => t.push(lstate),
+            Err(err) => {
+                ffi::lua_pushnil(lstate);
+                Ok(1 + err.push(lstate)?)
+            },
+        }
+    }
+}
+
+impl<K, V> Pushable for HashMap<K, V>
+where
+    K: Pushable,
+    V: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        push_pairs(self.len(), self, lstate)
+    }
+}
+
+impl<K, V> Pushable for BTreeMap<K, V>
+where
+    K: Pushable,
+    V: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        push_pairs(self.len(), self, lstate)
+    }
+}
+
+/// Pushes the set as a table like `{ foo = true, bar = true }`.
+impl<T> Pushable for HashSet<T>
+where
+    T: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        push_pairs(self.len(), self.into_iter().map(|t| (t, true)), lstate)
+    }
+}
+
+/// Pushes the set as a table like `{ foo = true, bar = true }`.
+impl<T> Pushable for BTreeSet<T>
+where
+    T: Pushable,
+{
+    unsafe fn push(
+        self,
+        lstate: *mut lua_State,
+    ) -> Result<c_int, crate::Error> {
+        push_pairs(self.len(), self.into_iter().map(|t| (t, true)), lstate)
+    }
+}
+
+/// Pushes a table containing the `len` key-value pairs yielded by `pairs`.
+unsafe fn push_pairs<K, V>(
+    len: usize,
+    pairs: impl IntoIterator<Item = (K, V)>,
+    lstate: *mut lua_State,
+) -> Result<c_int, crate::Error>
+where
+    K: Pushable,
+    V: Pushable,
+{
+    push_or_restore(lstate, || {
+        ffi::lua_createtable(lstate, 0, len as _);
+
+        for (key, value) in pairs {
+            push_one(key, lstate)?;
+            push_one(value, lstate)?;
+            ffi::lua_rawset(lstate, -3);
+        }
+
+        Ok(1)
+    })
+}
+
+/// Pushes an array-like table containing the given values.
+unsafe fn push_array<I>(
+    values: I,
+    lstate: *mut lua_State,
+) -> Result<c_int, crate::Error>
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: Pushable,
+{
+    let values = values.into_iter();
+
+    push_or_restore(lstate, || {
+        ffi::lua_createtable(lstate, values.len() as _, 0);
+
+        for (i, value) in values.enumerate() {
+            push_one(value, lstate)?;
             ffi::lua_rawseti(lstate, -2, (i + 1) as _);
         }
 
         Ok(1)
-    }
+    })
 }
 
 /// Implements `LuaPushable` for a tuple `(a, b, c, ..)` where all the elements
@@ -173,8 +450,9 @@ macro_rules! push_tuple {
                 lstate: *mut lua_State,
             ) -> Result<c_int, crate::Error> {
                 let ($($name,)*) = self;
-                $($name.push(lstate)?;)*
-                Ok(count!($($name)*))
+                let mut pushed = 0;
+                $(pushed += $name.push(lstate)?;)*
+                Ok(pushed)
             }
         }
     }
@@ -0,0 +1,138 @@
+use std::ffi::c_int;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::ffi::{self, lua_State};
+use crate::{Error, Poppable, Pushable};
+
+/// An owned reference to a value stored in the Lua registry.
+///
+/// The reference is released when the `RegistryRef` is dropped, and cloning
+/// it creates a new reference to the same value.
+///
+/// Two `RegistryRef`s are equal if the values they point to are primitively
+/// equal, i.e. without invoking the `__eq` metamethod.
+pub struct RegistryRef {
+    lua_ref: c_int,
+}
+
+impl fmt::Debug for RegistryRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RegistryRef({})", self.lua_ref)
+    }
+}
+
+impl Clone for RegistryRef {
+    fn clone(&self) -> Self {
+        unsafe {
+            crate::with_state(|lstate| {
+                self.push_value(lstate);
+                Self::from_stack(lstate)
+            })
+        }
+    }
+}
+
+impl Drop for RegistryRef {
+    fn drop(&mut self) {
+        // `luaL_unref` already ignores `LUA_NOREF` and `LUA_REFNIL`, but this
+        // avoids having to access the Lua state.
+        if self.lua_ref < 0 {
+            return;
+        }
+
+        unsafe {
+            crate::with_state(|lstate| {
+                ffi::luaL_unref(lstate, ffi::LUA_REGISTRYINDEX, self.lua_ref)
+            })
+        }
+    }
+}
+
+impl PartialEq for RegistryRef {
+    fn eq(&self, other: &Self) -> bool {
+        if self.lua_ref == other.lua_ref {
+            return true;
+        }
+
+        unsafe {
+            crate::with_state(|lstate| {
+                self.push_value(lstate);
+                other.push_value(lstate);
+                let eq = ffi::lua_rawequal(lstate, -1, -2) == 1;
+                ffi::lua_pop(lstate, 2);
+                eq
+            })
+        }
+    }
+}
+
+impl Eq for RegistryRef {}
+
+impl Hash for RegistryRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // This is null for values that aren't garbage collected, which is
+        // still consistent with `PartialEq`.
+        let ptr = unsafe {
+            crate::with_state(|lstate| {
+                self.push_value(lstate);
+                let ptr = ffi::lua_topointer(lstate, -1);
+                ffi::lua_pop(lstate, 1);
+                ptr
+            })
+        };
+        ptr.hash(state);
+    }
+}
+
+impl RegistryRef {
+    /// Pops the value at the top of the stack and stores it in the registry.
+    #[inline]
+    pub unsafe fn from_stack(lstate: *mut lua_State) -> Self {
+        Self { lua_ref: ffi::luaL_ref(lstate, ffi::LUA_REGISTRYINDEX) }
+    }
+
+    /// Takes ownership of a raw reference, which will be released when the
+    /// returned value is dropped.
+    #[inline]
+    pub unsafe fn from_raw(lua_ref: c_int) -> Self {
+        Self { lua_ref }
+    }
+
+    /// Returns the raw reference without giving up ownership.
+    #[inline]
+    pub fn as_raw(&self) -> c_int {
+        self.lua_ref
+    }
+
+    /// Returns the raw reference, which won't be released anymore.
+    #[inline]
+    pub fn into_raw(self) -> c_int {
+        let lua_ref = self.lua_ref;
+        std::mem::forget(self);
+        lua_ref
+    }
+
+    /// Pushes the referenced value on the stack.
+    #[inline]
+    pub unsafe fn push_value(&self, lstate: *mut lua_State) {
+        ffi::lua_rawgeti(lstate, ffi::LUA_REGISTRYINDEX, self.lua_ref);
+    }
+}
+
+impl Poppable for RegistryRef {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        Ok(Self::from_stack(lstate))
+    }
+}
+
+impl Pushable for RegistryRef {
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        self.push_value(lstate);
+        Ok(1)
+    }
+}
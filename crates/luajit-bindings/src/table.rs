@@ -0,0 +1,376 @@
+use std::ffi::c_int;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::ffi::{self, lua_State};
+use crate::{function, Error, Poppable, Pushable, RegistryRef};
+
+/// A handle to a Lua table stored in the Lua registry.
+///
+/// Unlike popping a table into a `HashMap` or a `Vec`, creating a `Table`
+/// doesn't copy any of its contents, which are only read on demand.
+#[derive(Clone)]
+pub struct Table {
+    lua_ref: RegistryRef,
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<table {}>", self.lua_ref.as_raw())
+    }
+}
+
+impl Default for Table {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    /// Creates a new empty table.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_capacity(0, 0)
+    }
+
+    /// Creates a new empty table with space preallocated for `narr` array
+    /// elements and `nrec` non-array elements.
+    pub fn with_capacity(narr: usize, nrec: usize) -> Self {
+        unsafe {
+            crate::with_state(|lstate| {
+                ffi::lua_createtable(lstate, narr as _, nrec as _);
+                Self { lua_ref: RegistryRef::from_stack(lstate) }
+            })
+        }
+    }
+
+    /// Returns the value associated to `key`, possibly triggering the
+    /// `__index` metamethod. Errors raised by the metamethod are returned
+    /// instead of unwinding through Rust.
+    pub fn get<K, V>(&self, key: K) -> Result<V, Error>
+    where
+        K: Pushable,
+        V: Poppable,
+    {
+        self.get_with(key, gettable)
+    }
+
+    /// Same as [`get`](Table::get) but without invoking metamethods.
+    pub fn raw_get<K, V>(&self, key: K) -> Result<V, Error>
+    where
+        K: Pushable,
+        V: Poppable,
+    {
+        self.get_with(key, rawget)
+    }
+
+    /// Sets `table[key] = value`, possibly triggering the `__newindex`
+    /// metamethod. Errors raised by the metamethod, or caused by a `nil` or
+    /// NaN key, are returned instead of unwinding through Rust.
+    pub fn set<K, V>(&self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Pushable,
+        V: Pushable,
+    {
+        self.set_with(key, value, settable)
+    }
+
+    /// Same as [`set`](Table::set) but without invoking metamethods. Setting a
+    /// `nil` or NaN key still fails.
+    pub fn raw_set<K, V>(&self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Pushable,
+        V: Pushable,
+    {
+        self.set_with(key, value, rawset)
+    }
+
+    /// Returns the length of the table as given by the `#` operator, without
+    /// invoking the `__len` metamethod.
+    pub fn raw_len(&self) -> usize {
+        unsafe {
+            crate::with_state(|lstate| {
+                self.push_self(lstate);
+                let len = ffi::lua_objlen(lstate, -1);
+                ffi::lua_pop(lstate, 1);
+                len
+            })
+        }
+    }
+
+    /// Returns the table's metatable, if it has one.
+    pub fn metatable(&self) -> Option<Table> {
+        unsafe {
+            crate::with_state(|lstate| {
+                self.push_self(lstate);
+                let metatable =
+                    (ffi::lua_getmetatable(lstate, -1) != 0).then(|| Self {
+                        lua_ref: RegistryRef::from_stack(lstate),
+                    });
+                ffi::lua_pop(lstate, 1);
+                metatable
+            })
+        }
+    }
+
+    /// Sets or removes the table's metatable.
+    pub fn set_metatable(&self, metatable: Option<Table>) {
+        unsafe {
+            crate::with_state(|lstate| {
+                self.push_self(lstate);
+                match metatable {
+                    Some(metatable) => metatable.push_self(lstate),
+                    None => ffi::lua_pushnil(lstate),
+                }
+                ffi::lua_setmetatable(lstate, -2);
+                ffi::lua_pop(lstate, 1);
+            })
+        }
+    }
+
+    /// Returns an iterator over all the key-value pairs of the table, in the
+    /// same order as Lua's `pairs`.
+    pub fn pairs<K, V>(&self) -> Pairs<'_, K, V>
+    where
+        K: Poppable,
+        V: Poppable,
+    {
+        Pairs { table: self, key_ref: None, done: false, _pd: PhantomData }
+    }
+
+    /// Returns an iterator over the values at indices `1..n`, where `n + 1` is
+    /// the first index whose value is `nil`, just like Lua's `ipairs`.
+    pub fn ipairs<V>(&self) -> Ipairs<'_, V>
+    where
+        V: Poppable,
+    {
+        Ipairs { table: self, index: 0, done: false, _pd: PhantomData }
+    }
+
+    fn get_with<K, V>(
+        &self,
+        key: K,
+        getter: ffi::lua_CFunction,
+    ) -> Result<V, Error>
+    where
+        K: Pushable,
+        V: Poppable,
+    {
+        unsafe {
+            crate::with_state(|lstate| {
+                let top = ffi::lua_gettop(lstate);
+                ffi::lua_pushcfunction(lstate, getter);
+                self.push_self(lstate);
+                let value = crate::push_one(key, lstate).and_then(|_| {
+                    match ffi::lua_pcall(lstate, 2, 1, 0) {
+                        ffi::LUA_OK => V::pop(lstate),
+                        err_code => Err(function::pop_error(lstate, err_code)),
+                    }
+                });
+                // Pop anything left over if something failed.
+                ffi::lua_settop(lstate, top);
+                value
+            })
+        }
+    }
+
+    fn set_with<K, V>(
+        &self,
+        key: K,
+        value: V,
+        setter: ffi::lua_CFunction,
+    ) -> Result<(), Error>
+    where
+        K: Pushable,
+        V: Pushable,
+    {
+        unsafe {
+            crate::with_state(|lstate| {
+                let top = ffi::lua_gettop(lstate);
+                ffi::lua_pushcfunction(lstate, setter);
+                self.push_self(lstate);
+                let res = crate::push_one(key, lstate)
+                    .and_then(|_| crate::push_one(value, lstate))
+                    .and_then(|_| match ffi::lua_pcall(lstate, 3, 0, 0) {
+                        ffi::LUA_OK => Ok(()),
+                        err_code => Err(function::pop_error(lstate, err_code)),
+                    });
+                ffi::lua_settop(lstate, top);
+                res
+            })
+        }
+    }
+
+    /// Pushes the table on the stack without consuming the handle.
+    unsafe fn push_self(&self, lstate: *mut lua_State) {
+        self.lua_ref.push_value(lstate);
+    }
+}
+
+/// Indexes the table passed as the first argument with the second one,
+/// possibly triggering the `__index` metamethod.
+unsafe extern "C" fn gettable(lstate: *mut lua_State) -> c_int {
+    ffi::lua_gettable(lstate, 1);
+    1
+}
+
+/// Same as [`gettable`] but without invoking metamethods.
+unsafe extern "C" fn rawget(lstate: *mut lua_State) -> c_int {
+    ffi::lua_rawget(lstate, 1);
+    1
+}
+
+/// Sets `t[k] = v`, where `t`, `k` and `v` are the three arguments, possibly
+/// triggering the `__newindex` metamethod.
+unsafe extern "C" fn settable(lstate: *mut lua_State) -> c_int {
+    ffi::lua_settable(lstate, 1);
+    0
+}
+
+/// Same as [`settable`] but without invoking metamethods.
+unsafe extern "C" fn rawset(lstate: *mut lua_State) -> c_int {
+    ffi::lua_rawset(lstate, 1);
+    0
+}
+
+impl Poppable for Table {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        match ffi::lua_type(lstate, -1) {
+            ffi::LUA_TTABLE => {
+                Ok(Self { lua_ref: RegistryRef::from_stack(lstate) })
+            },
+
+            _ => Err(Error::pop_wrong_value::<Self>(
+                lstate,
+                -1,
+                ffi::LUA_TTABLE,
+            )),
+        }
+    }
+}
+
+impl Pushable for Table {
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        self.push_self(lstate);
+        Ok(1)
+    }
+}
+
+/// An iterator over the key-value pairs of a [`Table`].
+///
+/// This `struct` is created by the [`pairs`](Table::pairs) method on
+/// [`Table`].
+pub struct Pairs<'a, K, V> {
+    table: &'a Table,
+
+    /// A reference to the last key returned by `lua_next`, or `None` if the
+    /// iteration hasn't started yet.
+    key_ref: Option<RegistryRef>,
+
+    done: bool,
+
+    _pd: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> Iterator for Pairs<'a, K, V>
+where
+    K: Poppable,
+    V: Poppable,
+{
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        unsafe {
+            crate::with_state(|lstate| {
+                self.table.push_self(lstate);
+
+                match self.key_ref.take() {
+                    Some(key_ref) => key_ref.push_value(lstate),
+                    None => ffi::lua_pushnil(lstate),
+                }
+
+                if ffi::lua_next(lstate, -2) == 0 {
+                    // Pop the table.
+                    ffi::lua_pop(lstate, 1);
+                    self.done = true;
+                    return None;
+                }
+
+                // Store a copy of the key for the next call to `lua_next`
+                // before `K::pop` gets a chance to modify it.
+                ffi::lua_pushvalue(lstate, -2);
+                self.key_ref = Some(RegistryRef::from_stack(lstate));
+
+                let top = ffi::lua_gettop(lstate);
+
+                let pair = V::pop(lstate)
+                    .map_err(|err| err.at_key(lstate, top - 1))
+                    .and_then(|value| K::pop(lstate).map(|key| (key, value)));
+
+                // Pop the table together with anything that was left on the
+                // stack if popping the key or the value failed.
+                ffi::lua_settop(lstate, top - 3);
+
+                Some(pair)
+            })
+        }
+    }
+}
+
+/// An iterator over the array part of a [`Table`].
+///
+/// This `struct` is created by the [`ipairs`](Table::ipairs) method on
+/// [`Table`].
+pub struct Ipairs<'a, V> {
+    table: &'a Table,
+    index: c_int,
+    done: bool,
+    _pd: PhantomData<V>,
+}
+
+impl<'a, V> Iterator for Ipairs<'a, V>
+where
+    V: Poppable,
+{
+    type Item = Result<V, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        unsafe {
+            crate::with_state(|lstate| {
+                self.table.push_self(lstate);
+
+                let top = ffi::lua_gettop(lstate);
+
+                self.index += 1;
+                ffi::lua_rawgeti(lstate, -1, self.index);
+
+                if ffi::lua_type(lstate, -1) == ffi::LUA_TNIL {
+                    ffi::lua_settop(lstate, top - 1);
+                    self.done = true;
+                    return None;
+                }
+
+                let value = V::pop(lstate)
+                    .map_err(|err| err.at_index(self.index as i64));
+
+                // Pop the table.
+                ffi::lua_settop(lstate, top - 1);
+
+                Some(value)
+            })
+        }
+    }
+}
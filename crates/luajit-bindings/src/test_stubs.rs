@@ -0,0 +1,41 @@
+//! Stubs of the Lua C API functions reachable from the types of the crates
+//! depending on this one, e.g. the `Drop` and `PartialEq` impls of objects
+//! holding a `LuaRef`.
+//!
+//! Unit tests run outside of Neovim, where the Lua C API is not available,
+//! and their binaries need these symbols to link. They're only compiled with
+//! the `test-stubs` feature, which must never be enabled outside of
+//! `[dev-dependencies]` as the stubs would shadow the real functions.
+
+use std::ffi::c_int;
+
+use crate::ffi::lua_State;
+
+#[no_mangle]
+unsafe extern "C" fn lua_rawequal(
+    _: *mut lua_State,
+    _: c_int,
+    _: c_int,
+) -> c_int {
+    unreachable!("the Lua C API is not available in unit tests")
+}
+
+#[no_mangle]
+unsafe extern "C" fn lua_rawgeti(_: *mut lua_State, _: c_int, _: c_int) {
+    unreachable!("the Lua C API is not available in unit tests")
+}
+
+#[no_mangle]
+unsafe extern "C" fn lua_settop(_: *mut lua_State, _: c_int) {
+    unreachable!("the Lua C API is not available in unit tests")
+}
+
+#[no_mangle]
+unsafe extern "C" fn luaL_ref(_: *mut lua_State, _: c_int) -> c_int {
+    unreachable!("the Lua C API is not available in unit tests")
+}
+
+#[no_mangle]
+unsafe extern "C" fn luaL_unref(_: *mut lua_State, _: c_int, _: c_int) {
+    unreachable!("the Lua C API is not available in unit tests")
+}
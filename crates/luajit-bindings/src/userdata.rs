@@ -0,0 +1,428 @@
+use std::any::TypeId;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::ffi::c_int;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::{fmt, mem, ptr};
+
+use crate::ffi::{self, lua_State};
+use crate::function::{self, Callback};
+use crate::{Error, Poppable, Pushable, RegistryRef};
+
+/// What's actually stored in the memory block of the userdata. It's set to
+/// `None` by the `__gc` metamethod.
+type Payload<T> = Option<Rc<RefCell<T>>>;
+
+thread_local! {
+    /// Registry references to the metatables of the `UserData` types that
+    /// have been pushed to Lua at least once.
+    static METATABLES: RefCell<HashMap<TypeId, c_int>> =
+        RefCell::new(HashMap::new());
+}
+
+/// A Rust type that can be moved to Lua as a full userdata.
+///
+/// The value is kept alive for as long as it's referenced from either Lua or
+/// a [`UserDataRef`], and it's dropped when both the userdata gets garbage
+/// collected and all the `UserDataRef`s pointing to it are gone.
+///
+/// Accessing the value from its methods is borrow-checked at runtime, so a
+/// method taking `&mut self` that ends up calling itself from Lua will raise
+/// an error instead of aliasing the value.
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::convert::Infallible;
+///
+/// use nvim_oxi::lua::{MetaMethod, UserData, UserDataMethods};
+///
+/// struct Counter(u32);
+///
+/// impl UserData for Counter {
+///     fn add_methods(methods: &mut UserDataMethods<Self>) {
+///         methods.add_method("get", |this, ()| Ok::<_, Infallible>(this.0));
+///
+///         methods.add_method_mut("incr", |this, by: Option<u32>| {
+///             this.0 += by.unwrap_or(1);
+///             Ok::<_, Infallible>(())
+///         });
+///
+///         methods.add_meta_method(MetaMethod::ToString, |this, ()| {
+///             Ok::<_, Infallible>(format!("Counter({})", this.0))
+///         });
+///     }
+/// }
+/// ```
+pub trait UserData: Sized + 'static {
+    /// Registers the methods and metamethods available from Lua. This is
+    /// only called once per type, the first time a value is pushed.
+    fn add_methods(_methods: &mut UserDataMethods<Self>) {}
+}
+
+/// The metamethods that can be registered on a [`UserData`].
+///
+/// `__gc` is missing because it's always used to drop the value, while
+/// `__index` falls back to the function registered here only if the key
+/// doesn't match any of the methods.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MetaMethod {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Unm,
+    Concat,
+    Len,
+    Eq,
+    Lt,
+    Le,
+    Index,
+    NewIndex,
+    Call,
+    ToString,
+}
+
+impl MetaMethod {
+    /// Returns the name of the metamethod, e.g. `"__index"`.
+    pub fn name(&self) -> &'static str {
+        use MetaMethod::*;
+        match self {
+            Add => "__add",
+            Sub => "__sub",
+            Mul => "__mul",
+            Div => "__div",
+            Mod => "__mod",
+            Pow => "__pow",
+            Unm => "__unm",
+            Concat => "__concat",
+            Len => "__len",
+            Eq => "__eq",
+            Lt => "__lt",
+            Le => "__le",
+            Index => "__index",
+            NewIndex => "__newindex",
+            Call => "__call",
+            ToString => "__tostring",
+        }
+    }
+}
+
+/// Collects the methods and metamethods of a [`UserData`] type.
+///
+/// The `this` argument passed to every callback is the userdata the method
+/// is called on, i.e. the first argument when calling `ud:method(..)` from
+/// Lua. Binary metamethods are only called if the userdata is the left
+/// operand.
+pub struct UserDataMethods<T> {
+    methods: Vec<(String, Callback)>,
+    meta_methods: Vec<(MetaMethod, Callback)>,
+    _pd: PhantomData<T>,
+}
+
+impl<T: UserData> UserDataMethods<T> {
+    /// Registers a method taking a shared reference to the value.
+    pub fn add_method<M, A, R, E>(&mut self, name: &str, method: M)
+    where
+        M: Fn(&T, A) -> Result<R, E> + 'static,
+        A: Poppable,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        self.methods.push((name.to_owned(), method_callback(method)));
+    }
+
+    /// Registers a method taking an exclusive reference to the value.
+    pub fn add_method_mut<M, A, R, E>(&mut self, name: &str, method: M)
+    where
+        M: Fn(&mut T, A) -> Result<R, E> + 'static,
+        A: Poppable,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        self.methods.push((name.to_owned(), method_mut_callback(method)));
+    }
+
+    /// Registers a metamethod taking a shared reference to the value.
+    pub fn add_meta_method<M, A, R, E>(&mut self, meta: MetaMethod, method: M)
+    where
+        M: Fn(&T, A) -> Result<R, E> + 'static,
+        A: Poppable,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        self.meta_methods.push((meta, method_callback(method)));
+    }
+
+    /// Registers a metamethod taking an exclusive reference to the value.
+    pub fn add_meta_method_mut<M, A, R, E>(
+        &mut self,
+        meta: MetaMethod,
+        method: M,
+    ) where
+        M: Fn(&mut T, A) -> Result<R, E> + 'static,
+        A: Poppable,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        self.meta_methods.push((meta, method_mut_callback(method)));
+    }
+}
+
+/// Turns a method into a `Callback` expecting the userdata as its first
+/// argument.
+fn method_callback<T, M, A, R, E>(method: M) -> Callback
+where
+    T: UserData,
+    M: Fn(&T, A) -> Result<R, E> + 'static,
+    A: Poppable,
+    R: Pushable,
+    E: StdError + 'static,
+{
+    Box::new(move |lstate| unsafe {
+        let this = pop_this::<T>(lstate)?;
+        let args = A::pop(lstate)?;
+        let this = this.try_borrow().map_err(borrow_error::<T, _>)?;
+        let ret =
+            method(&this, args).map_err(Error::push_error_from_err::<R, _>)?;
+        drop(this);
+        ret.push(lstate).map(Into::into)
+    })
+}
+
+/// Same as [`method_callback`] for methods taking `&mut T`.
+fn method_mut_callback<T, M, A, R, E>(method: M) -> Callback
+where
+    T: UserData,
+    M: Fn(&mut T, A) -> Result<R, E> + 'static,
+    A: Poppable,
+    R: Pushable,
+    E: StdError + 'static,
+{
+    Box::new(move |lstate| unsafe {
+        let this = pop_this::<T>(lstate)?;
+        let args = A::pop(lstate)?;
+        let mut this = this.try_borrow_mut().map_err(borrow_error::<T, _>)?;
+        let ret = method(&mut this, args)
+            .map_err(Error::push_error_from_err::<R, _>)?;
+        drop(this);
+        ret.push(lstate).map(Into::into)
+    })
+}
+
+fn borrow_error<T, E: StdError>(err: E) -> Error {
+    Error::runtime_error(format!("{}: {err}", std::any::type_name::<T>()))
+}
+
+/// Removes the userdata at the bottom of the stack, returning the value it
+/// holds.
+///
+/// The returned `Rc` keeps the value alive even if the userdata gets
+/// collected while the method is running.
+unsafe fn pop_this<T: UserData>(
+    lstate: *mut lua_State,
+) -> Result<Rc<RefCell<T>>, Error> {
+    let this = get_data::<T>(lstate, 1)?;
+    ffi::lua_remove(lstate, 1);
+    Ok(this)
+}
+
+/// Returns the value held by the userdata at index `idx` if it is a `T`.
+unsafe fn get_data<T: UserData>(
+    lstate: *mut lua_State,
+    idx: c_int,
+) -> Result<Rc<RefCell<T>>, Error> {
+    let is_t = ffi::lua_type(lstate, idx) == ffi::LUA_TUSERDATA
+        && ffi::lua_getmetatable(lstate, idx) != 0
+        && {
+            push_metatable::<T>(lstate);
+            let is_t = ffi::lua_rawequal(lstate, -1, -2) != 0;
+            ffi::lua_pop(lstate, 2);
+            is_t
+        };
+
+    if !is_t {
+        return Err(Error::pop_wrong_type_at_idx::<T>(lstate, idx));
+    }
+
+    let payload = ffi::lua_touserdata(lstate, idx) as *mut Payload<T>;
+
+    (*payload).clone().ok_or_else(|| {
+        Error::pop_error(
+            std::any::type_name::<T>(),
+            "the userdata has already been garbage collected",
+        )
+    })
+}
+
+/// Pushes the metatable of `T` on the stack, creating it if this is the first
+/// time it's needed.
+unsafe fn push_metatable<T: UserData>(lstate: *mut lua_State) {
+    let type_id = TypeId::of::<T>();
+
+    if let Some(mt_ref) =
+        METATABLES.with(|mts| mts.borrow().get(&type_id).copied())
+    {
+        ffi::lua_rawgeti(lstate, ffi::LUA_REGISTRYINDEX, mt_ref);
+        return;
+    }
+
+    let mut methods = UserDataMethods::<T> {
+        methods: Vec::new(),
+        meta_methods: Vec::new(),
+        _pd: PhantomData,
+    };
+    T::add_methods(&mut methods);
+
+    let UserDataMethods { methods, mut meta_methods, .. } = methods;
+
+    let index = meta_methods
+        .iter()
+        .position(|(meta, _)| *meta == MetaMethod::Index)
+        .map(|pos| meta_methods.remove(pos).1);
+
+    ffi::lua_createtable(lstate, 0, meta_methods.len() as c_int + 2);
+
+    "__gc".push(lstate).unwrap();
+    ffi::lua_pushcfunction(lstate, gc::<T>);
+    ffi::lua_rawset(lstate, -3);
+
+    if !methods.is_empty() || index.is_some() {
+        "__index".push(lstate).unwrap();
+
+        if !methods.is_empty() {
+            ffi::lua_createtable(lstate, 0, methods.len() as c_int);
+            for (name, method) in methods {
+                name.push(lstate).unwrap();
+                function::push_callback(lstate, method);
+                ffi::lua_rawset(lstate, -3);
+            }
+        }
+
+        if let Some(index) = index {
+            function::push_callback(lstate, index);
+            if ffi::lua_type(lstate, -2) == ffi::LUA_TTABLE {
+                ffi::lua_pushcclosure(lstate, index_fallback, 2);
+            }
+        }
+
+        ffi::lua_rawset(lstate, -3);
+    }
+
+    for (meta, method) in meta_methods {
+        meta.name().push(lstate).unwrap();
+        function::push_callback(lstate, method);
+        ffi::lua_rawset(lstate, -3);
+    }
+
+    ffi::lua_pushvalue(lstate, -1);
+    let mt_ref = ffi::luaL_ref(lstate, ffi::LUA_REGISTRYINDEX);
+    METATABLES.with(|mts| mts.borrow_mut().insert(type_id, mt_ref));
+}
+
+/// The `__index` metamethod used when a type has both methods and an
+/// `__index` metamethod. The methods table and the metamethod are stored as
+/// the first and second upvalues, respectively.
+unsafe extern "C" fn index_fallback(lstate: *mut lua_State) -> c_int {
+    // Stack: [userdata, key].
+    ffi::lua_pushvalue(lstate, 2);
+    ffi::lua_rawget(lstate, ffi::lua_upvalueindex(1));
+
+    if ffi::lua_type(lstate, -1) != ffi::LUA_TNIL {
+        return 1;
+    }
+
+    ffi::lua_pop(lstate, 1);
+    ffi::lua_pushvalue(lstate, ffi::lua_upvalueindex(2));
+    ffi::lua_insert(lstate, 1);
+    ffi::lua_call(lstate, 2, 1);
+    1
+}
+
+/// The `__gc` metamethod, dropping Lua's reference to the value.
+unsafe extern "C" fn gc<T: 'static>(lstate: *mut lua_State) -> c_int {
+    let payload = ffi::lua_touserdata(lstate, 1) as *mut Payload<T>;
+    if let Some(payload) = payload.as_mut() {
+        // Raising an error from `__gc` would surface wherever the collection
+        // happened to run, so we report it on the next loop iteration.
+        if let Err(err) = crate::utils::catch_panic(|| *payload = None) {
+            crate::utils::schedule_error(lstate, &err);
+        }
+    }
+    0
+}
+
+/// A handle to a [`UserData`] value living in Lua.
+///
+/// Cloning the handle is cheap and doesn't clone the value.
+pub struct UserDataRef<T: UserData> {
+    lua_ref: RegistryRef,
+    data: Rc<RefCell<T>>,
+}
+
+impl<T: UserData> fmt::Debug for UserDataRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<userdata {}>", std::any::type_name::<T>())
+    }
+}
+
+impl<T: UserData> Clone for UserDataRef<T> {
+    fn clone(&self) -> Self {
+        Self { lua_ref: self.lua_ref.clone(), data: self.data.clone() }
+    }
+}
+
+impl<T: UserData> UserDataRef<T> {
+    /// Moves `value` to Lua, creating a new userdata.
+    pub fn new(value: T) -> Self {
+        let data = Rc::new(RefCell::new(value));
+
+        unsafe {
+            crate::with_state(|lstate| {
+                let payload =
+                    ffi::lua_newuserdata(lstate, mem::size_of::<Payload<T>>())
+                        as *mut Payload<T>;
+
+                ptr::write(payload, Some(data.clone()));
+
+                push_metatable::<T>(lstate);
+                ffi::lua_setmetatable(lstate, -2);
+
+                Self { lua_ref: RegistryRef::from_stack(lstate), data }
+            })
+        }
+    }
+
+    /// Immutably borrows the value, failing if it's currently mutably
+    /// borrowed.
+    pub fn borrow(&self) -> Result<Ref<'_, T>, Error> {
+        self.data.try_borrow().map_err(borrow_error::<T, _>)
+    }
+
+    /// Mutably borrows the value, failing if it's currently borrowed.
+    pub fn borrow_mut(&self) -> Result<RefMut<'_, T>, Error> {
+        self.data.try_borrow_mut().map_err(borrow_error::<T, _>)
+    }
+}
+
+impl<T: UserData> Poppable for UserDataRef<T> {
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        if ffi::lua_gettop(lstate) == 0 {
+            return Err(Error::PopEmptyStack);
+        }
+
+        let data = get_data::<T>(lstate, -1)?;
+        Ok(Self { lua_ref: RegistryRef::from_stack(lstate), data })
+    }
+}
+
+impl<T: UserData> Pushable for UserDataRef<T> {
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        self.lua_ref.push_value(lstate);
+        Ok(1)
+    }
+}
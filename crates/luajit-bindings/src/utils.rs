@@ -1,5 +1,8 @@
-use std::ffi::{c_int, CStr};
-use std::fmt::Display;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_int, c_void, CStr};
+use std::fmt::{Display, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
 
 use crate::ffi::{self, lua_State};
 
@@ -17,18 +20,211 @@ pub unsafe fn debug_value(
     lstate: *mut lua_State,
     n: c_int,
 ) -> Box<dyn Display> {
-    match ffi::lua_type(lstate, n) {
-        ffi::LUA_TNONE | ffi::LUA_TNIL => Box::new("()"),
+    Box::new(inspect(lstate, n))
+}
+
+/// How many levels of nested tables are shown by [`inspect`].
+const INSPECT_DEPTH: usize = 8;
+
+/// How many entries of a table are shown before eliding the rest with `...`.
+const INSPECT_MAX_ENTRIES: usize = 32;
+
+/// Returns a `vim.inspect`-like, single line representation of the Lua value
+/// at a given stack index. Tables are walked recursively, references to a
+/// table from inside itself are shown as `<cycle>`.
+///
+/// To keep the output readable, tables nested more than 8 levels deep are
+/// shown as `{...}`, and only the first 32 entries of a table are shown.
+pub unsafe fn inspect(lstate: *mut lua_State, idx: c_int) -> String {
+    inspect_with_depth(lstate, idx, INSPECT_DEPTH)
+}
 
-        ffi::LUA_TBOOLEAN => Box::new(ffi::lua_toboolean(lstate, n) == 1),
+/// Same as [`inspect`], but tables nested more than `depth` levels deep are
+/// shown as `{...}`.
+pub unsafe fn inspect_with_depth(
+    lstate: *mut lua_State,
+    idx: c_int,
+    depth: usize,
+) -> String {
+    // Make the index absolute since we'll be pushing values on the stack.
+    let idx = if idx < 0 && idx > ffi::LUA_REGISTRYINDEX {
+        ffi::lua_gettop(lstate) + idx + 1
+    } else {
+        idx
+    };
+
+    let mut out = String::new();
+    inspect_value(lstate, idx, depth, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Writes the representation of the value at the absolute index `idx` to
+/// `out`. `parents` contains the addresses of the tables we're currently
+/// inside of, and is used to detect cycles.
+unsafe fn inspect_value(
+    lstate: *mut lua_State,
+    idx: c_int,
+    depth: usize,
+    parents: &mut Vec<*const c_void>,
+    out: &mut String,
+) {
+    match ffi::lua_type(lstate, idx) {
+        ffi::LUA_TNONE | ffi::LUA_TNIL => out.push_str("nil"),
 
-        ffi::LUA_TSTRING => Box::new(
-            CStr::from_ptr(ffi::lua_tostring(lstate, n)).to_string_lossy(),
-        ),
+        ffi::LUA_TBOOLEAN => {
+            out.push_str(if ffi::lua_toboolean(lstate, idx) == 1 {
+                "true"
+            } else {
+                "false"
+            })
+        },
 
-        ffi::LUA_TNUMBER => Box::new(ffi::lua_tonumber(lstate, n)),
+        ffi::LUA_TNUMBER => {
+            let n = ffi::lua_tonumber(lstate, idx);
+            // Integers are printed without a trailing `.0`, like Lua does.
+            if n.fract() == 0.0 && n.abs() < 2f64.powi(53) {
+                let _ = write!(out, "{}", n as i64);
+            } else {
+                let _ = write!(out, "{n}");
+            }
+        },
 
-        _ => Box::new("other"),
+        ffi::LUA_TSTRING => {
+            let mut len = 0;
+            let ptr = ffi::lua_tolstring(lstate, idx, &mut len);
+            let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+            let _ = write!(out, "{:?}", String::from_utf8_lossy(bytes));
+        },
+
+        ffi::LUA_TTABLE => inspect_table(lstate, idx, depth, parents, out),
+
+        // Functions, userdata, threads and cdata.
+        _ => {
+            let _ = write!(
+                out,
+                "<{}: {:p}>",
+                debug_type(lstate, idx),
+                ffi::lua_topointer(lstate, idx)
+            );
+        },
+    }
+}
+
+unsafe fn inspect_table(
+    lstate: *mut lua_State,
+    idx: c_int,
+    depth: usize,
+    parents: &mut Vec<*const c_void>,
+    out: &mut String,
+) {
+    let ptr = ffi::lua_topointer(lstate, idx);
+
+    if parents.contains(&ptr) {
+        out.push_str("<cycle>");
+        return;
+    }
+
+    // We need at most 3 more slots: the key, the value, and the key of a
+    // nested table.
+    if depth == 0 || ffi::lua_checkstack(lstate, 3) == 0 {
+        out.push_str("{...}");
+        return;
+    }
+
+    parents.push(ptr);
+
+    let inspect_top = |parents: &mut Vec<*const c_void>| {
+        let mut value = String::new();
+        let top = ffi::lua_gettop(lstate);
+        inspect_value(lstate, top, depth - 1, parents, &mut value);
+        value
+    };
+
+    let mut entries = Vec::new();
+
+    // The array part goes first, without the keys.
+    let mut len = 0;
+    while entries.len() < INSPECT_MAX_ENTRIES {
+        ffi::lua_rawgeti(lstate, idx, len + 1);
+        if ffi::lua_type(lstate, -1) == ffi::LUA_TNIL {
+            ffi::lua_pop(lstate, 1);
+            break;
+        }
+        entries.push(inspect_top(parents));
+        ffi::lua_pop(lstate, 1);
+        len += 1;
+    }
+
+    // Then all the other keys, sorted to get a stable output.
+    let mut fields = Vec::new();
+    let mut elided = false;
+
+    ffi::lua_pushnil(lstate);
+    while ffi::lua_next(lstate, idx) != 0 {
+        let key_idx = ffi::lua_gettop(lstate) - 1;
+
+        let key = match ffi::lua_type(lstate, key_idx) {
+            ffi::LUA_TNUMBER => {
+                let n = ffi::lua_tonumber(lstate, key_idx);
+                if n.fract() == 0.0 && n >= 1.0 && n <= len as f64 {
+                    ffi::lua_pop(lstate, 1);
+                    continue;
+                }
+                None
+            },
+
+            ffi::LUA_TSTRING => {
+                let mut len = 0;
+                let ptr = ffi::lua_tolstring(lstate, key_idx, &mut len);
+                let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+                std::str::from_utf8(bytes)
+                    .ok()
+                    .filter(|key| crate::path::is_identifier(key))
+                    .map(ToOwned::to_owned)
+            },
+
+            _ => None,
+        };
+
+        if entries.len() + fields.len() == INSPECT_MAX_ENTRIES {
+            // Pop both the key and the value to stop the iteration.
+            ffi::lua_pop(lstate, 2);
+            elided = true;
+            break;
+        }
+
+        let key = match key {
+            Some(key) => key,
+            None => {
+                let mut key = String::from("[");
+                inspect_value(lstate, key_idx, depth - 1, parents, &mut key);
+                key.push(']');
+                key
+            },
+        };
+
+        fields.push(format!("{key} = {}", inspect_top(parents)));
+        ffi::lua_pop(lstate, 1);
+    }
+
+    fields.sort();
+    entries.extend(fields);
+
+    if elided {
+        entries.push("...".to_owned());
+    }
+
+    if ffi::lua_getmetatable(lstate, idx) != 0 {
+        entries.push(format!("<metatable> = {}", inspect_top(parents)));
+        ffi::lua_pop(lstate, 1);
+    }
+
+    parents.pop();
+
+    if entries.is_empty() {
+        out.push_str("{}");
+    } else {
+        let _ = write!(out, "{{ {} }}", entries.join(", "));
     }
 }
 
@@ -63,7 +259,7 @@ pub unsafe fn debug_stack(lstate: *mut lua_State) {
     let stack_pp = (1..height + 1)
         .map(|n| {
             let idx = height + 1 - n;
-            let value = debug_value(lstate, -n);
+            let value = inspect(lstate, -n);
             let typename = debug_type(lstate, -n);
             format!("{idx}: {value} ({typename})")
         })
@@ -82,6 +278,115 @@ pub unsafe fn handle_error<E: std::error::Error + ?Sized>(
     ffi::lua_error(lstate);
 }
 
+/// Pushes a closure raising `err` to `vim.schedule`.
+///
+/// Used to report errors in callbacks that aren't called from Lua, and that
+/// can't therefore raise a Lua error directly. If `vim.schedule` fails the
+/// message is written with `nvim_err_writeln` instead, and if that fails too
+/// it's dropped.
+pub unsafe fn schedule_error<E: std::error::Error + ?Sized>(
+    lstate: *mut lua_State,
+    err: &E,
+) {
+    unsafe extern "C" fn raise(lstate: *mut lua_State) -> c_int {
+        ffi::lua_pushvalue(lstate, ffi::lua_upvalueindex(1));
+        ffi::lua_error(lstate);
+    }
+
+    // Both reporters are called in protected mode with the message as their
+    // only argument, so that an error in them doesn't unwind through Rust.
+    unsafe extern "C" fn schedule(lstate: *mut lua_State) -> c_int {
+        ffi::lua_getglobal(lstate, crate::macros::cstr!("vim"));
+        ffi::lua_getfield(lstate, -1, crate::macros::cstr!("schedule"));
+        ffi::lua_pushvalue(lstate, 1);
+        ffi::lua_pushcclosure(lstate, raise, 1);
+        ffi::lua_call(lstate, 1, 0);
+        0
+    }
+
+    unsafe extern "C" fn err_writeln(lstate: *mut lua_State) -> c_int {
+        ffi::lua_getglobal(lstate, crate::macros::cstr!("vim"));
+        ffi::lua_getfield(lstate, -1, crate::macros::cstr!("api"));
+        ffi::lua_getfield(
+            lstate,
+            -1,
+            crate::macros::cstr!("nvim_err_writeln"),
+        );
+        ffi::lua_pushvalue(lstate, 1);
+        ffi::lua_call(lstate, 1, 0);
+        0
+    }
+
+    let msg = err.to_string();
+
+    for report in [schedule, err_writeln] {
+        ffi::lua_pushcfunction(lstate, report);
+        ffi::lua_pushlstring(lstate, msg.as_ptr() as *const _, msg.len());
+
+        if ffi::lua_pcall(lstate, 1, 0, 0) == ffi::LUA_OK {
+            return;
+        }
+
+        // Pop the error.
+        ffi::lua_pop(lstate, 1);
+    }
+}
+
+thread_local! {
+    /// How many calls to `catch_panic` are currently on the stack.
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+
+    /// The location of the last panic caught by `catch_panic`.
+    static PANIC_LOCATION: RefCell<Option<String>> =
+        const { RefCell::new(None) };
+}
+
+/// Calls `fun`, turning a panic into a
+/// [`RuntimeError`](crate::Error::RuntimeError) containing the panic's
+/// message and location.
+///
+/// Rust callbacks called by Lua or libuv should always be wrapped in this, as
+/// unwinding across the C boundary would bring down Neovim. Panics caught
+/// here are not printed to stderr.
+pub fn catch_panic<F, R>(fun: F) -> Result<R, crate::Error>
+where
+    F: FnOnce() -> R,
+{
+    static SET_HOOK: Once = Once::new();
+
+    SET_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.with(Cell::get) > 0 {
+                let location = info.location().map(ToString::to_string);
+                PANIC_LOCATION.with(|loc| *loc.borrow_mut() = location);
+            } else {
+                default_hook(info)
+            }
+        }));
+    });
+
+    CATCHING.with(|c| c.set(c.get() + 1));
+    let res = panic::catch_unwind(AssertUnwindSafe(fun));
+    CATCHING.with(|c| c.set(c.get() - 1));
+
+    res.map_err(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+
+        let msg = match PANIC_LOCATION.with(|loc| loc.borrow_mut().take()) {
+            Some(location) => format!("panicked at {location}: {msg}"),
+            None => format!("panicked: {msg}"),
+        };
+
+        crate::Error::runtime_error(msg)
+    })
+}
+
 pub fn type_name(ty: c_int) -> &'static str {
     match ty {
         ffi::LUA_TNONE => "empty stack",
@@ -94,6 +399,7 @@ pub fn type_name(ty: c_int) -> &'static str {
         ffi::LUA_TFUNCTION => "function",
         ffi::LUA_TUSERDATA => "userdata",
         ffi::LUA_TTHREAD => "thread",
+        ffi::LUA_TCDATA => "cdata",
         _ => unreachable!(),
     }
 }
@@ -0,0 +1,118 @@
+use std::ffi::c_int;
+use std::ops::{Deref, DerefMut};
+
+use crate::ffi::{self, lua_State};
+use crate::{Error, Poppable, Pushable};
+
+/// A variable number of values of the same type.
+///
+/// When popped it consumes all the values left on the stack, so it can be
+/// used as the last element of a tuple of arguments to accept any number of
+/// trailing arguments, like Lua's `...`. When pushed it pushes each value
+/// separately, so it can be used to return zero or more values.
+///
+/// # Examples
+///
+/// ```ignore
+/// use nvim_oxi::lua::Variadic;
+/// use nvim_oxi::Function;
+///
+/// let sum = Function::from_fn(|(init, nums): (i32, Variadic<i32>)| {
+///     Ok::<_, nvim_oxi::Error>(init + nums.iter().sum::<i32>())
+/// });
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Variadic<T>(Vec<T>);
+
+impl<T> Variadic<T> {
+    /// Creates an empty `Variadic`.
+    #[inline]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the inner vector.
+    #[inline]
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Variadic<T> {
+    type Target = Vec<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Variadic<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Variadic<T> {
+    #[inline]
+    fn from(vec: Vec<T>) -> Self {
+        Self(vec)
+    }
+}
+
+impl<T> FromIterator<T> for Variadic<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for Variadic<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Poppable for Variadic<T>
+where
+    T: Poppable,
+{
+    unsafe fn pop(lstate: *mut lua_State) -> Result<Self, Error> {
+        Self::pop_reserving(lstate, 0)
+    }
+
+    unsafe fn pop_reserving(
+        lstate: *mut lua_State,
+        reserved: c_int,
+    ) -> Result<Self, Error> {
+        let n = (ffi::lua_gettop(lstate) - reserved).max(0);
+
+        let mut values =
+            (0..n).map(|_| T::pop(lstate)).collect::<Result<Vec<_>, _>>()?;
+
+        // The values were popped starting from the last one.
+        values.reverse();
+
+        Ok(Self(values))
+    }
+}
+
+impl<T> Pushable for Variadic<T>
+where
+    T: Pushable,
+{
+    unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, Error> {
+        let mut n = 0;
+
+        for value in self {
+            n += value.push(lstate)?;
+        }
+
+        Ok(n)
+    }
+}
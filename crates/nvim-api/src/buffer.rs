@@ -134,10 +134,7 @@ impl Buffer {
         let mut err = nvim::Error::new();
         let obj = unsafe { nvim_buf_call(self.0, fun.lua_ref(), &mut err) };
 
-        choose!(err, {
-            fun.remove_from_lua_registry();
-            Ok(R::from_object(obj)?)
-        })
+        choose!(err, Ok(R::from_object(obj)?))
     }
 
     /// Binding to [`nvim_buf_create_user_command`](https://neovim.io/doc/user/api.html#nvim_buf_create_user_command()).
@@ -94,7 +94,9 @@ pub(crate) struct KeyDict_keymap<'a> {
     unique: Object,
     nowait: Object,
     noremap: Object,
-    callback: NonOwning<'a, Object>,
+    // Neovim takes ownership of the callback's reference, so we give it its
+    // own.
+    callback: Object,
     #[cfg(any(feature = "neovim-0-8", feature = "neovim-nightly"))]
     replace_keycodes: Object,
 }
@@ -109,7 +111,7 @@ impl<'a> From<&'a SetKeymapOpts> for KeyDict_keymap<'a> {
             unique: opts.unique.into(),
             nowait: opts.nowait.into(),
             noremap: opts.noremap.into(),
-            callback: opts.callback.non_owning(),
+            callback: opts.callback.clone(),
             #[cfg(any(feature = "neovim-0-8", feature = "neovim-nightly"))]
             replace_keycodes: opts.replace_keycodes.into(),
         }
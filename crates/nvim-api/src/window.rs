@@ -97,10 +97,7 @@ impl Window {
         let mut err = nvim::Error::new();
         let obj = unsafe { nvim_win_call(self.0, fun.lua_ref(), &mut err) };
 
-        choose!(err, {
-            fun.remove_from_lua_registry();
-            Ok(R::from_object(obj)?)
-        })
+        choose!(err, Ok(R::from_object(obj)?))
     }
 
     /// Binding to [`nvim_win_close`](https://neovim.io/doc/user/api.html#nvim_win_close()).
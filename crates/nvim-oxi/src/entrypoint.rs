@@ -22,8 +22,9 @@ where
     #[cfg(feature = "libuv")]
     libuv_bindings::init(lua_state);
 
-    match body() {
-        Ok(api) => api.push(lua_state).unwrap(),
-        Err(err) => lua::utils::handle_error(lua_state, &err),
+    match lua::utils::catch_panic(body) {
+        Ok(Ok(api)) => api.push(lua_state).unwrap(),
+        Ok(Err(err)) => lua::utils::handle_error(lua_state, &err),
+        Err(panic) => lua::utils::handle_error(lua_state, &panic),
     }
 }
@@ -13,6 +13,7 @@
 
 mod entrypoint;
 mod error;
+mod load;
 mod toplevel;
 
 pub mod api {
@@ -34,12 +35,25 @@ pub mod libuv {
     pub use libuv_bindings::*;
 }
 
+pub mod conversion {
+    //! Traits for converting between Neovim [`Object`](crate::Object)s and
+    //! Rust types.
+    #[doc(inline)]
+    pub use nvim_types::conversion::*;
+    #[doc(inline)]
+    pub use oxi_derive::{FromObject, ToObject};
+}
+
 pub mod lua {
     //! Low-level Rust bindings to [LuaJIT], the Lua version used by Neovim.
     //!
     //! [LuaJIT]: https://luajit.org/
     #[doc(inline)]
     pub use luajit_bindings::*;
+    #[doc(inline)]
+    pub use oxi_derive::{Poppable, Pushable};
+
+    pub use crate::load::load;
 }
 
 #[cfg(feature = "mlua")]
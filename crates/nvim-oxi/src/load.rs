@@ -0,0 +1,32 @@
+use luajit_bindings::{self as lua, Poppable};
+use nvim_types::Function;
+
+/// Loads a chunk of Lua code without running it, returning it as a
+/// [`Function`] that can be called later.
+///
+/// `name` is used as the chunk name in error messages and tracebacks. Like
+/// Lua's `load`, names starting with `=` are shown as they are.
+///
+/// # Examples
+///
+/// ```ignore
+/// use nvim_oxi::lua;
+///
+/// let add = lua::load::<(i32, i32), i32>(
+///     "local a, b = ...; return a + b",
+///     "=add",
+/// )?;
+///
+/// assert_eq!(3, add.call((1, 2))?);
+/// ```
+pub fn load<A, R>(
+    chunk: &str,
+    name: &str,
+) -> Result<Function<A, R>, lua::Error> {
+    unsafe {
+        lua::with_state(|lstate| {
+            lua::load_chunk(lstate, chunk, name)?;
+            Function::pop(lstate)
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use luajit_bindings::{self as lua, ffi::*, macros::cstr};
+use luajit_bindings as lua;
 use nvim_types::Function;
 
 use crate::Result;
@@ -18,22 +18,18 @@ where
     //
     // Unfortunately the `nlua_schedule` C function is not exported, so we have
     // to call the Lua function instead.
-    unsafe {
-        lua::with_state(move |lstate| {
-            // Put `vim.schedule` on the stack.
-            lua_getglobal(lstate, cstr!("vim"));
-            lua_getfield(lstate, -1, cstr!("schedule"));
+    let res = lua::get_path::<Function<Function<(), ()>, ()>>("vim.schedule")
+        .and_then(|schedule| {
+            // Our reference to `fun` is released when it's dropped,
+            // `vim.schedule` keeps its own.
+            schedule.call(Function::from_fn_once(fun))
+        });
 
-            // Store the function in the registry and put a reference to it on
-            // the stack.
-            let fun = Function::from_fn_once(fun);
-            lua_rawgeti(lstate, LUA_REGISTRYINDEX, fun.lua_ref());
-
-            lua_call(lstate, 1, 0);
-
-            // Pop `vim` off the stack and remove the function from the registry.
-            lua_pop(lstate, 1);
-            luaL_unref(lstate, LUA_REGISTRYINDEX, fun.lua_ref());
-        })
-    };
+    // There's no way to return the error, so report it like the errors of
+    // the scheduled callbacks themselves.
+    if let Err(err) = res {
+        unsafe {
+            lua::with_state(|lstate| lua::utils::schedule_error(lstate, &err))
+        }
+    }
 }
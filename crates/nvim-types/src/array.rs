@@ -36,6 +36,40 @@ impl lua::Poppable for Array {
     }
 }
 
+impl Array {
+    /// Inserts an object at position `idx`, shifting all the objects after
+    /// it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx > len`.
+    #[inline]
+    pub fn insert<V: Into<Object>>(&mut self, idx: usize, value: V) {
+        self.insert_at(idx, value.into())
+    }
+
+    /// Removes and returns the object at position `idx`, shifting all the
+    /// objects after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    #[inline]
+    pub fn remove(&mut self, idx: usize) -> Object {
+        self.remove_at(idx)
+    }
+
+    /// Retains only the objects for which `fun` returns `true`, preserving
+    /// their order.
+    #[inline]
+    pub fn retain<F>(&mut self, mut fun: F)
+    where
+        F: FnMut(&Object) -> bool,
+    {
+        self.retain_mut(|obj| fun(obj))
+    }
+}
+
 impl IntoIterator for Array {
     type IntoIter = ArrayIterator;
     type Item = <ArrayIterator as Iterator>::Item;
@@ -46,7 +80,27 @@ impl IntoIterator for Array {
         let arr = ManuallyDrop::new(self);
         let start = arr.items;
         let end = unsafe { start.add(arr.len()) };
-        ArrayIterator { start, end }
+        ArrayIterator { buf: start, start, end }
+    }
+}
+
+impl<'a> IntoIterator for &'a Array {
+    type IntoIter = std::slice::Iter<'a, Object>;
+    type Item = &'a Object;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Array {
+    type IntoIter = std::slice::IterMut<'a, Object>;
+    type Item = &'a mut Object;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -65,6 +119,7 @@ where
 
 /// An owning iterator over the [`Object`]s of a Neovim [`Array`].
 pub struct ArrayIterator {
+    buf: *mut Object,
     start: *const Object,
     end: *const Object,
 }
@@ -106,8 +161,8 @@ impl DoubleEndedIterator for ArrayIterator {
         if self.start == self.end {
             return None;
         }
-        let current = self.end;
         self.end = unsafe { self.end.offset(-1) };
+        let current = self.end;
         Some(unsafe { ptr::read(current) })
     }
 }
@@ -122,6 +177,7 @@ impl Drop for ArrayIterator {
                 self.start = self.start.offset(1);
             }
         }
+        unsafe { crate::kvec::free(self.buf) };
     }
 }
 
@@ -178,6 +234,36 @@ mod tests {
         assert_eq!(Some(Object::from("Foo")), iter.next());
     }
 
+    #[test]
+    fn iter_back() {
+        let array = Array::from_iter(["Foo", "Bar", "Baz"]);
+
+        let mut iter = array.into_iter();
+        assert_eq!(Some(Object::from("Baz")), iter.next_back());
+        assert_eq!(Some(Object::from("Foo")), iter.next());
+        assert_eq!(Some(Object::from("Bar")), iter.next_back());
+        assert_eq!(None, iter.next_back());
+    }
+
+    #[test]
+    fn mutate_in_place() {
+        let mut array = Array::new();
+        array.push(Object::from(3));
+        array.extend([Object::from(1), Object::from(2)]);
+        array.insert(0, "a");
+        assert_eq!(Object::from("a"), array.remove(0));
+
+        for obj in &mut array {
+            *obj = Object::from(obj.as_integer().unwrap() * 10);
+        }
+        array.sort_by_key(|obj| obj.as_integer());
+
+        assert_eq!(Array::from((10, 20, 30)), array);
+        assert_eq!(Some(Object::from(30)), array.pop());
+        array.retain(|obj| obj != &Object::from(20));
+        assert_eq!([Object::from(10)], array[..]);
+    }
+
     #[test]
     fn empty_array() {
         let empty = Array { size: 0, capacity: 0, items: ptr::null_mut() };
@@ -1,6 +1,8 @@
 //! Traits for converting between Neovim [`Object`]s and Rust types.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
 
 use thiserror::Error as ThisError;
 
@@ -20,12 +22,25 @@ pub enum Error {
     #[error("Was expecting a \"{expected}\" but received a \"{actual}\"")]
     FromWrongType { expected: &'static str, actual: &'static str },
 
+    #[error("Was expecting {expected} elements but received {actual}")]
+    FromWrongLength { expected: usize, actual: usize },
+
+    #[error("Missing field \"{field}\"")]
+    MissingField { field: &'static str },
+
+    #[error("Unknown variant \"{variant}\"")]
+    UnknownVariant { variant: String },
+
     #[error(transparent)]
     FromInt(#[from] std::num::TryFromIntError),
 
     #[error(transparent)]
     FromUtf8(#[from] std::string::FromUtf8Error),
 
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    Json(#[from] crate::json::Error),
+
     #[cfg(feature = "serde")]
     #[error(transparent)]
     Serde(#[from] crate::serde::Error),
@@ -121,7 +136,7 @@ impl FromObject for Array {
             ObjectKind::Array => Ok(unsafe { obj.into_array_unchecked() }),
 
             other => Err(Error::FromWrongType {
-                expected: "string",
+                expected: "array",
                 actual: other.as_static(),
             }),
         }
@@ -134,7 +149,7 @@ impl FromObject for Dictionary {
             ObjectKind::Dictionary => Ok(unsafe { obj.into_dict_unchecked() }),
 
             other => Err(Error::FromWrongType {
-                expected: "string",
+                expected: "dictionary",
                 actual: other.as_static(),
             }),
         }
@@ -145,7 +160,7 @@ impl<A, R> FromObject for Function<A, R> {
     fn from_object(obj: Object) -> Result<Self, Error> {
         match obj.kind() {
             ObjectKind::LuaRef => {
-                Ok(Self::from_ref(unsafe { obj.as_luaref_unchecked() }))
+                Ok(Self::from_ref(unsafe { obj.into_luaref_unchecked() }))
             },
 
             other => Err(Error::FromWrongType {
@@ -225,6 +240,141 @@ where
     }
 }
 
+impl<T, const N: usize> FromObject for [T; N]
+where
+    T: FromObject,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        Vec::<T>::from_object(obj)?.try_into().map_err(|vec: Vec<T>| {
+            Error::FromWrongLength { expected: N, actual: vec.len() }
+        })
+    }
+}
+
+impl<T> FromObject for Box<T>
+where
+    T: FromObject,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        T::from_object(obj).map(Box::new)
+    }
+}
+
+impl<T> FromObject for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: FromObject,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        T::Owned::from_object(obj).map(Cow::Owned)
+    }
+}
+
+impl<K, V> FromObject for HashMap<K, V>
+where
+    K: FromObject + Eq + Hash,
+    V: FromObject,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        Dictionary::from_object(obj)?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_object(k.into())?, V::from_object(v)?)))
+            .collect()
+    }
+}
+
+impl<K, V> FromObject for BTreeMap<K, V>
+where
+    K: FromObject + Ord,
+    V: FromObject,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        Dictionary::from_object(obj)?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_object(k.into())?, V::from_object(v)?)))
+            .collect()
+    }
+}
+
+/// Gets a set from a dictionary like `{ foo = true, bar = true }`. Keys whose
+/// value is `nil` or `false` are skipped.
+impl<T> FromObject for HashSet<T>
+where
+    T: FromObject + Eq + Hash,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        Dictionary::from_object(obj)?
+            .into_iter()
+            .filter(|(_, v)| is_truthy(v))
+            .map(|(k, _)| T::from_object(k.into()))
+            .collect()
+    }
+}
+
+/// Gets a set from a dictionary like `{ foo = true, bar = true }`. Keys whose
+/// value is `nil` or `false` are skipped.
+impl<T> FromObject for BTreeSet<T>
+where
+    T: FromObject + Ord,
+{
+    fn from_object(obj: Object) -> Result<Self, Error> {
+        Dictionary::from_object(obj)?
+            .into_iter()
+            .filter(|(_, v)| is_truthy(v))
+            .map(|(k, _)| T::from_object(k.into()))
+            .collect()
+    }
+}
+
+/// Whether the object is neither `nil` nor `false`, same as in Lua.
+fn is_truthy(obj: &Object) -> bool {
+    match obj.kind() {
+        ObjectKind::Nil => false,
+        ObjectKind::Boolean => unsafe { obj.as_boolean_unchecked() },
+        _ => true,
+    }
+}
+
+/// Implements `FromObject` for a tuple `(A, B, C, ..)` where all the elements
+/// in the tuple implement `FromObject`. The tuple is obtained from an
+/// [`Array`] with the same number of elements.
+macro_rules! tuple_from_object {
+    ($($name:ident)*) => {
+        impl<$($name,)*> FromObject for ($($name,)*)
+        where
+            $($name: FromObject,)*
+        {
+            #[allow(non_snake_case)]
+            fn from_object(obj: Object) -> Result<Self, Error> {
+                let [$($name,)*] = Vec::<Object>::from(Array::from_object(obj)?)
+                    .try_into()
+                    .map_err(|vec: Vec<Object>| Error::FromWrongLength {
+                        expected: [$(stringify!($name),)*].len(),
+                        actual: vec.len(),
+                    })?;
+                Ok(($($name::from_object($name)?,)*))
+            }
+        }
+    };
+}
+
+tuple_from_object!(A);
+tuple_from_object!(A B);
+tuple_from_object!(A B C);
+tuple_from_object!(A B C D);
+tuple_from_object!(A B C D E);
+tuple_from_object!(A B C D E F);
+tuple_from_object!(A B C D E F G);
+tuple_from_object!(A B C D E F G H);
+tuple_from_object!(A B C D E F G H I);
+tuple_from_object!(A B C D E F G H I J);
+tuple_from_object!(A B C D E F G H I J K);
+tuple_from_object!(A B C D E F G H I J K L);
+tuple_from_object!(A B C D E F G H I J K L M);
+tuple_from_object!(A B C D E F G H I J K L M N);
+tuple_from_object!(A B C D E F G H I J K L M N O);
+tuple_from_object!(A B C D E F G H I J K L M N O P);
+
 impl<T> ToObject for T
 where
     T: Into<Object>,
@@ -276,3 +426,257 @@ where
             .map(Into::into)
     }
 }
+
+impl<K, V> ToObject for BTreeMap<K, V>
+where
+    K: Into<crate::String>,
+    V: ToObject,
+{
+    fn to_object(self) -> Result<Object, Error> {
+        self.into_iter()
+            .map(|(k, v)| Ok((k, v.to_object()?)))
+            .collect::<Result<Dictionary, Error>>()
+            .map(Into::into)
+    }
+}
+
+/// Converts the set into a dictionary like `{ foo = true, bar = true }`.
+impl<T> ToObject for HashSet<T>
+where
+    T: Into<crate::String>,
+{
+    fn to_object(self) -> Result<Object, Error> {
+        Ok(self.into_iter().map(|t| (t, true)).collect::<Dictionary>().into())
+    }
+}
+
+/// Converts the set into a dictionary like `{ foo = true, bar = true }`.
+impl<T> ToObject for BTreeSet<T>
+where
+    T: Into<crate::String>,
+{
+    fn to_object(self) -> Result<Object, Error> {
+        Ok(self.into_iter().map(|t| (t, true)).collect::<Dictionary>().into())
+    }
+}
+
+impl<T, const N: usize> ToObject for [T; N]
+where
+    T: ToObject,
+{
+    fn to_object(self) -> Result<Object, Error> {
+        Vec::from(self).to_object()
+    }
+}
+
+/// Implements `ToObject` for a tuple `(A, B, C, ..)` where all the elements
+/// in the tuple implement `ToObject`, converting it into an [`Array`].
+macro_rules! tuple_to_object {
+    ($($name:ident)*) => {
+        impl<$($name,)*> ToObject for ($($name,)*)
+        where
+            $($name: ToObject,)*
+        {
+            #[allow(non_snake_case)]
+            fn to_object(self) -> Result<Object, Error> {
+                let ($($name,)*) = self;
+                Ok(Array::from_iter([$($name.to_object()?,)*]).into())
+            }
+        }
+    };
+}
+
+tuple_to_object!(A);
+tuple_to_object!(A B);
+tuple_to_object!(A B C);
+tuple_to_object!(A B C D);
+tuple_to_object!(A B C D E);
+tuple_to_object!(A B C D E F);
+tuple_to_object!(A B C D E F G);
+tuple_to_object!(A B C D E F G H);
+tuple_to_object!(A B C D E F G H I);
+tuple_to_object!(A B C D E F G H I J);
+tuple_to_object!(A B C D E F G H I J K);
+tuple_to_object!(A B C D E F G H I J K L);
+tuple_to_object!(A B C D E F G H I J K L M);
+tuple_to_object!(A B C D E F G H I J K L M N);
+tuple_to_object!(A B C D E F G H I J K L M N O);
+tuple_to_object!(A B C D E F G H I J K L M N O P);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_roundtrip() {
+        let set = BTreeSet::from(["bar".to_owned(), "foo".to_owned()]);
+        let obj = set.clone().to_object().unwrap();
+        assert_eq!(Ok(set), BTreeSet::from_object(obj));
+
+        let dict = Dictionary::from_iter([("foo", true), ("bar", false)]);
+        assert_eq!(
+            Ok(BTreeSet::from(["foo".to_owned()])),
+            BTreeSet::<String>::from_object(dict.into())
+        );
+    }
+
+    #[test]
+    fn tuple_and_array_lengths() {
+        let obj = (1, "foo", true).to_object().unwrap();
+        assert_eq!(
+            Ok((1, "foo".to_owned(), true)),
+            <(u8, String, bool)>::from_object(obj.clone())
+        );
+        assert_eq!(
+            Err(Error::FromWrongLength { expected: 2, actual: 3 }),
+            <(Object, Object)>::from_object(obj)
+        );
+
+        let obj = [1, 2, 3].to_object().unwrap();
+        assert_eq!(Ok([1, 2, 3]), <[u32; 3]>::from_object(obj.clone()));
+        assert_eq!(
+            Err(Error::FromWrongLength { expected: 4, actual: 3 }),
+            <[u32; 4]>::from_object(obj)
+        );
+    }
+
+    mod derive {
+        use oxi_derive::{FromObject, ToObject};
+
+        use super::*;
+
+        #[derive(Debug, PartialEq, FromObject, ToObject)]
+        #[object(crate = "crate", rename_all = "camelCase")]
+        struct Popup {
+            max_height: Option<u32>,
+
+            #[object(rename = "title")]
+            name: String,
+
+            #[object(default = "ten")]
+            width: u64,
+
+            border: Border,
+
+            #[object(skip)]
+            is_open: bool,
+
+            #[object(flatten)]
+            extra: BTreeMap<String, Object>,
+        }
+
+        fn ten() -> u64 {
+            10
+        }
+
+        #[derive(Clone, Debug, PartialEq, FromObject, ToObject)]
+        #[object(crate = "crate", rename_all = "snake_case")]
+        enum Border {
+            None,
+            Custom(Vec<String>),
+            Shadow {
+                blend: u8,
+            },
+            #[object(rename = "pad")]
+            Padding(u8, u8),
+        }
+
+        #[derive(Debug, PartialEq, FromObject, ToObject)]
+        #[object(crate = "crate")]
+        struct Point(i32, #[object(skip)] u8, i32);
+
+        #[test]
+        fn struct_to_object() {
+            let popup = Popup {
+                max_height: None,
+                name: "foo".into(),
+                width: 3,
+                border: Border::Shadow { blend: 20 },
+                is_open: true,
+                extra: BTreeMap::from([("zindex".into(), Object::from(50))]),
+            };
+
+            let expected = Dictionary::from_iter([
+                ("title", Object::from("foo")),
+                ("width", Object::from(3)),
+                (
+                    "border",
+                    Dictionary::from_iter([(
+                        "shadow",
+                        Dictionary::from_iter([("blend", 20)]),
+                    )])
+                    .into(),
+                ),
+                ("zindex", Object::from(50)),
+            ]);
+
+            assert_eq!(Ok(expected.into()), popup.to_object());
+        }
+
+        #[test]
+        fn struct_from_object() {
+            let dict = Dictionary::from_iter([
+                ("title", Object::from("foo")),
+                ("maxHeight", Object::from(5)),
+                ("border", Object::from("none")),
+                ("zindex", Object::from(50)),
+            ]);
+
+            let popup = Popup {
+                max_height: Some(5),
+                name: "foo".into(),
+                width: 10,
+                border: Border::None,
+                is_open: false,
+                extra: BTreeMap::from([("zindex".into(), Object::from(50))]),
+            };
+
+            assert_eq!(Ok(popup), Popup::from_object(dict.into()));
+
+            let dict = Dictionary::from_iter([("border", "none")]);
+            assert_eq!(
+                Err(Error::MissingField { field: "title" }),
+                Popup::from_object(dict.into())
+            );
+        }
+
+        #[test]
+        fn enum_roundtrip() {
+            for border in [
+                Border::None,
+                Border::Custom(vec!["+".into(), "-".into()]),
+                Border::Shadow { blend: 0 },
+                Border::Padding(1, 2),
+            ] {
+                let obj = border.clone().to_object().unwrap();
+                assert_eq!(Ok(border), Border::from_object(obj));
+            }
+
+            let obj = Border::Padding(1, 2).to_object().unwrap();
+            assert_eq!(
+                Some(true),
+                obj.as_dict().map(|d| d.contains_key("pad"))
+            );
+
+            assert_eq!(
+                Err(Error::UnknownVariant { variant: "double".into() }),
+                Border::from_object("double".into())
+            );
+
+            assert_eq!(
+                Err(Error::FromWrongType {
+                    expected: "string or dictionary",
+                    actual: "integer"
+                }),
+                Border::from_object(1.into())
+            );
+        }
+
+        #[test]
+        fn tuple_struct_skip() {
+            let obj = Point(1, 7, -1).to_object().unwrap();
+            assert_eq!(Object::from(Array::from((1, -1))), obj);
+            assert_eq!(Ok(Point(1, 0, -1)), Point::from_object(obj));
+        }
+    }
+}
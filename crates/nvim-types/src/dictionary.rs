@@ -44,29 +44,504 @@ where
     }
 }
 
+impl KeyValuePair {
+    /// Returns the key of the pair.
+    #[inline]
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+
+    /// Returns the value of the pair.
+    #[inline]
+    pub fn value(&self) -> &Object {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the value of the pair.
+    #[inline]
+    pub fn value_mut(&mut self) -> &mut Object {
+        &mut self.value
+    }
+}
+
 impl Dictionary {
+    /// Returns a reference to the value associated to the key, if any.
+    ///
+    /// This does a linear scan of the dictionary, see [`DictIndex`] to do
+    /// many lookups or insertions on a large dictionary.
     pub fn get<Q>(&self, query: &Q) -> Option<&Object>
     where
         String: PartialEq<Q>,
+        Q: ?Sized,
     {
-        self.iter()
-            .find_map(|pair| (&pair.key == query).then_some(&pair.value))
+        self.position(query).map(|idx| &self.as_slice()[idx].value)
     }
 
+    /// Returns a mutable reference to the value associated to the key, if
+    /// any.
     pub fn get_mut<Q>(&mut self, query: &Q) -> Option<&mut Object>
     where
         String: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        self.position(query).map(|idx| &mut self.as_mut_slice()[idx].value)
+    }
+
+    /// Returns `true` if the dictionary contains a value for the key.
+    pub fn contains_key<Q>(&self, query: &Q) -> bool
+    where
+        String: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        self.position(query).is_some()
+    }
+
+    /// Inserts a key-value pair in the dictionary. If the key was already
+    /// present its value is replaced and the old one is returned, otherwise
+    /// the pair is added at the end.
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<Object>
+    where
+        K: Into<String>,
+        V: Into<Object>,
+    {
+        let key = key.into();
+        let value = value.into();
+
+        match self.get_mut(&key) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.push(KeyValuePair { key, value });
+                None
+            },
+        }
+    }
+
+    /// Removes the key from the dictionary, returning its value if it was
+    /// present. The order of the other pairs is preserved.
+    pub fn remove<Q>(&mut self, query: &Q) -> Option<Object>
+    where
+        String: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = self.position(query)?;
+        Some(self.remove_at(idx).value)
+    }
+
+    /// Retains only the pairs for which `fun` returns `true`, preserving
+    /// their order.
+    pub fn retain<F>(&mut self, mut fun: F)
+    where
+        F: FnMut(&String, &mut Object) -> bool,
+    {
+        self.retain_mut(|pair| fun(&pair.key, &mut pair.value));
+    }
+
+    /// Returns the entry for the key, to inspect or modify it in place.
+    pub fn entry<K: Into<String>>(&mut self, key: K) -> Entry<'_> {
+        let key = key.into();
+
+        match self.position(&key) {
+            Some(idx) => Entry::Occupied(OccupiedEntry { dict: self, idx }),
+            None => Entry::Vacant(VacantEntry { dict: self, key }),
+        }
+    }
+
+    /// Returns an iterator over the key-value pairs of the dictionary.
+    #[inline]
+    pub fn iter(&self) -> DictIter<'_> {
+        DictIter { iter: self.as_slice().iter() }
+    }
+
+    /// Returns an iterator over the key-value pairs of the dictionary, with
+    /// mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> DictIterMut<'_> {
+        DictIterMut { iter: self.as_mut_slice().iter_mut() }
+    }
+
+    /// Returns an iterator over the keys of the dictionary.
+    #[inline]
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &String> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the values of the dictionary.
+    #[inline]
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &Object> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Returns an iterator over mutable references to the values of the
+    /// dictionary.
+    #[inline]
+    pub fn values_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator<Item = &mut Object> + '_ {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    fn position<Q>(&self, query: &Q) -> Option<usize>
+    where
+        String: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        self.as_slice().iter().position(|pair| &pair.key == query)
+    }
+}
+
+/// A view into a single entry of a [`Dictionary`], which may either be
+/// occupied or vacant.
+///
+/// This `enum` is created by the [`entry`](Dictionary::entry) method on
+/// [`Dictionary`].
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+/// An entry of a [`Dictionary`] whose key is present.
+pub struct OccupiedEntry<'a> {
+    dict: &'a mut Dictionary,
+    idx: usize,
+}
+
+/// An entry of a [`Dictionary`] whose key is missing.
+pub struct VacantEntry<'a> {
+    dict: &'a mut Dictionary,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the entry's key.
+    #[inline]
+    pub fn key(&self) -> &String {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant, returning a mutable
+    /// reference to the value.
+    #[inline]
+    pub fn or_insert<V: Into<Object>>(self, default: V) -> &'a mut Object {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the entry is vacant, returning a
+    /// mutable reference to the value.
+    pub fn or_insert_with<F, V>(self, default: F) -> &'a mut Object
+    where
+        F: FnOnce() -> V,
+        V: Into<Object>,
+    {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Calls `fun` on the value if the entry is occupied.
+    pub fn and_modify<F>(mut self, fun: F) -> Self
+    where
+        F: FnOnce(&mut Object),
+    {
+        if let Self::Occupied(entry) = &mut self {
+            fun(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the entry's key.
+    #[inline]
+    pub fn key(&self) -> &String {
+        &self.dict.as_slice()[self.idx].key
+    }
+
+    /// Returns a reference to the entry's value.
+    #[inline]
+    pub fn get(&self) -> &Object {
+        &self.dict.as_slice()[self.idx].value
+    }
+
+    /// Returns a mutable reference to the entry's value.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut Object {
+        &mut self.dict.as_mut_slice()[self.idx].value
+    }
+
+    /// Converts the entry into a mutable reference to its value.
+    #[inline]
+    pub fn into_mut(self) -> &'a mut Object {
+        &mut self.dict.as_mut_slice()[self.idx].value
+    }
+
+    /// Replaces the entry's value, returning the old one.
+    #[inline]
+    pub fn insert<V: Into<Object>>(&mut self, value: V) -> Object {
+        std::mem::replace(self.get_mut(), value.into())
+    }
+
+    /// Removes the entry from the dictionary, returning its value.
+    #[inline]
+    pub fn remove(self) -> Object {
+        self.dict.remove_at(self.idx).value
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the key that would be used when inserting a value.
+    #[inline]
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+
+    /// Adds the key to the dictionary with the given value, returning a
+    /// mutable reference to it.
+    pub fn insert<V: Into<Object>>(self, value: V) -> &'a mut Object {
+        let Self { dict, key } = self;
+        dict.push(KeyValuePair { key, value: value.into() });
+        let last = dict.len() - 1;
+        &mut dict.as_mut_slice()[last].value
+    }
+}
+
+/// A [`Dictionary`] together with an index mapping its keys to their
+/// positions, to look them up and insert new pairs in constant time instead
+/// of scanning the whole dictionary.
+///
+/// Indexing an existing dictionary takes linear time, so it's only worth it
+/// when making many lookups or insertions on a large dictionary, like when
+/// building an option dictionary or processing the one returned by
+/// `get_all_options_info`. Removing a key still takes linear time.
+///
+/// If a key appears more than once only its first occurrence is indexed,
+/// same as [`Dictionary::get`].
+#[derive(Clone, Default)]
+pub struct DictIndex {
+    dict: Dictionary,
+    positions: HashMap<Box<[u8]>, usize>,
+}
+
+impl fmt::Debug for DictIndex {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.dict, f)
+    }
+}
+
+impl From<Dictionary> for DictIndex {
+    fn from(dict: Dictionary) -> Self {
+        let mut positions = HashMap::with_capacity(dict.len());
+        for (idx, pair) in dict.as_slice().iter().enumerate() {
+            positions.entry(pair.key.as_bytes().into()).or_insert(idx);
+        }
+        Self { dict, positions }
+    }
+}
+
+impl From<DictIndex> for Dictionary {
+    #[inline]
+    fn from(index: DictIndex) -> Self {
+        index.dict
+    }
+}
+
+impl DictIndex {
+    /// Creates a new, empty index.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the indexed dictionary.
+    #[inline]
+    pub fn as_dict(&self) -> &Dictionary {
+        &self.dict
+    }
+
+    /// Returns the indexed dictionary, dropping the index.
+    #[inline]
+    pub fn into_dict(self) -> Dictionary {
+        self.dict
+    }
+
+    /// Returns the number of pairs in the dictionary.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    /// Returns `true` if the dictionary contains no pairs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    /// Returns a reference to the value associated to the key, if any.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&Object>
+    where
+        Q: AsRef<[u8]> + ?Sized,
+    {
+        let idx = *self.positions.get(key.as_ref())?;
+        Some(&self.dict.as_slice()[idx].value)
+    }
+
+    /// Returns a mutable reference to the value associated to the key, if
+    /// any.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Object>
+    where
+        Q: AsRef<[u8]> + ?Sized,
+    {
+        let idx = *self.positions.get(key.as_ref())?;
+        Some(&mut self.dict.as_mut_slice()[idx].value)
+    }
+
+    /// Returns `true` if the dictionary contains a value for the key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: AsRef<[u8]> + ?Sized,
+    {
+        self.positions.contains_key(key.as_ref())
+    }
+
+    /// Same as [`Dictionary::insert`], but in constant time.
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<Object>
+    where
+        K: Into<String>,
+        V: Into<Object>,
+    {
+        let key = key.into();
+        let value = value.into();
+
+        if let Some(old) = self.get_mut(key.as_bytes()) {
+            return Some(std::mem::replace(old, value));
+        }
+
+        self.positions.insert(key.as_bytes().into(), self.dict.len());
+        Dictionary::push(&mut self.dict, KeyValuePair { key, value });
+        None
+    }
+
+    /// Same as [`Dictionary::remove`].
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Object>
+    where
+        Q: AsRef<[u8]> + ?Sized,
     {
+        let idx = self.positions.remove(key.as_ref())?;
+        let pair = self.dict.remove_at(idx);
+
+        for pos in self.positions.values_mut() {
+            if *pos > idx {
+                *pos -= 1;
+            }
+        }
+
+        // If the key appeared more than once its next occurrence is now the
+        // first one.
+        if let Some(next) = self.dict.as_slice()[idx..]
+            .iter()
+            .position(|other| other.key == pair.key)
+        {
+            self.positions.insert(pair.key.as_bytes().into(), idx + next);
+        }
+
+        Some(pair.value)
+    }
+}
+
+/// An iterator over the key-value pairs of a [`Dictionary`].
+///
+/// This `struct` is created by the [`iter`](Dictionary::iter) method on
+/// [`Dictionary`].
+#[derive(Clone)]
+pub struct DictIter<'a> {
+    iter: std::slice::Iter<'a, KeyValuePair>,
+}
+
+impl<'a> Iterator for DictIter<'a> {
+    type Item = (&'a String, &'a Object);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|pair| (&pair.key, &pair.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DictIter<'_> {}
+
+impl DoubleEndedIterator for DictIter<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|pair| (&pair.key, &pair.value))
+    }
+}
+
+/// An iterator over the key-value pairs of a [`Dictionary`], with mutable
+/// references to the values.
+///
+/// This `struct` is created by the [`iter_mut`](Dictionary::iter_mut) method
+/// on [`Dictionary`].
+pub struct DictIterMut<'a> {
+    iter: std::slice::IterMut<'a, KeyValuePair>,
+}
+
+impl<'a> Iterator for DictIterMut<'a> {
+    type Item = (&'a String, &'a mut Object);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|pair| (&pair.key, &mut pair.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DictIterMut<'_> {}
+
+impl DoubleEndedIterator for DictIterMut<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|pair| (&pair.key, &mut pair.value))
+    }
+}
+
+impl<'a> IntoIterator for &'a Dictionary {
+    type IntoIter = DictIter<'a>;
+    type Item = (&'a String, &'a Object);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Dictionary {
+    type IntoIter = DictIterMut<'a>;
+    type Item = (&'a String, &'a mut Object);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
         self.iter_mut()
-            .find_map(|pair| (&pair.key == query).then_some(&mut pair.value))
     }
 }
 
 impl fmt::Debug for Dictionary {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_map()
-            .entries(self.iter().map(|pair| (&pair.key, &pair.value)))
-            .finish()
+        f.debug_map().entries(self.iter()).finish()
     }
 }
 
@@ -121,13 +596,14 @@ impl IntoIterator for Dictionary {
         let start = arr.items;
         let end = unsafe { start.add(arr.len()) };
 
-        DictIterator { start, end }
+        DictIterator { buf: start, start, end }
     }
 }
 
 /// An owning iterator over the ([`String`], [`Object`]) pairs of a Neovim
 /// [`Dictionary`].
 pub struct DictIterator {
+    buf: *mut KeyValuePair,
     start: *const KeyValuePair,
     end: *const KeyValuePair,
 }
@@ -171,8 +647,8 @@ impl DoubleEndedIterator for DictIterator {
         if self.start == self.end {
             return None;
         }
-        let current = self.end;
         self.end = unsafe { self.end.offset(-1) };
+        let current = self.end;
         let KeyValuePair { key, value } = unsafe { ptr::read(current) };
         Some((key, value))
     }
@@ -184,10 +660,11 @@ impl Drop for DictIterator {
     fn drop(&mut self) {
         while self.start != self.end {
             unsafe {
-                ptr::drop_in_place(self.start as *mut Object);
+                ptr::drop_in_place(self.start as *mut KeyValuePair);
                 self.start = self.start.offset(1);
             }
         }
+        unsafe { crate::kvec::free(self.buf) };
     }
 }
 
@@ -218,7 +695,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Dictionary, Object, String as NvimString};
+    use super::{DictIndex, Dictionary, Object, String as NvimString};
 
     #[test]
     fn iter_basic() {
@@ -282,4 +759,88 @@ mod tests {
 
         assert_eq!(String::from("{foo: {a: 1}}"), format!("{dict}"));
     }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut dict = Dictionary::new();
+        assert_eq!(None, dict.insert("foo", 1));
+        assert_eq!(None, dict.insert("bar", 2));
+        assert_eq!(Some(Object::from(1)), dict.insert("foo", 3));
+
+        assert_eq!(2, dict.len());
+        assert!(dict.contains_key("bar"));
+        assert_eq!(Some(Object::from(2)), dict.remove("bar"));
+        assert_eq!(None, dict.remove("bar"));
+        assert_eq!(String::from("{foo: 3}"), format!("{dict}"));
+    }
+
+    #[test]
+    fn entry() {
+        let mut dict = Dictionary::from_iter([("foo", 1)]);
+
+        *dict.entry("bar").or_insert(0).as_integer_mut().unwrap() += 1;
+        dict.entry("foo")
+            .and_modify(|obj| *obj = Object::from(true))
+            .or_insert(42);
+
+        assert_eq!(String::from("{foo: true, bar: 1}"), format!("{dict}"));
+    }
+
+    #[test]
+    fn retain_and_iter() {
+        let mut dict =
+            Dictionary::from_iter([("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+
+        dict.retain(|_, obj| obj.as_integer().unwrap() % 2 == 0);
+        for (_, obj) in &mut dict {
+            *obj = Object::from(obj.as_integer().unwrap() * 10);
+        }
+
+        assert_eq!(
+            vec!["b", "d"],
+            dict.keys().map(|k| k.as_str().unwrap()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![20, 40],
+            dict.values().map(|v| v.as_integer().unwrap()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn dict_index() {
+        let dict = Dictionary::from_iter(
+            (0..100).map(|i| (format!("key{i}"), Object::from(i))),
+        );
+        let mut index = DictIndex::from(dict);
+
+        assert_eq!(Some(&Object::from(42)), index.get("key42"));
+        assert!(index.contains_key("key99"));
+        assert!(!index.contains_key("key100"));
+
+        assert_eq!(None, index.insert("key100", 100));
+        assert_eq!(Some(Object::from(0)), index.insert("key0", -1));
+        assert_eq!(Some(Object::from(50)), index.remove("key50"));
+        assert_eq!(None, index.remove("key50"));
+
+        assert_eq!(100, index.len());
+        assert_eq!(Some(&Object::from(-1)), index.get("key0"));
+        assert_eq!(Some(&Object::from(99)), index.get("key99"));
+        assert_eq!(Some(&Object::from(100)), index.get("key100"));
+
+        let dict = index.into_dict();
+        assert_eq!(Some(&Object::from(51)), dict.get("key51"));
+        assert_eq!(Some(&Object::from(100)), dict.get("key100"));
+    }
+
+    #[test]
+    fn dict_index_duplicate_keys() {
+        let mut dict = Dictionary::from_iter([("a", 1), ("b", 2)]);
+        dict.push(("a", 3).into());
+
+        let mut index = DictIndex::from(dict);
+        assert_eq!(Some(&Object::from(1)), index.get("a"));
+        assert_eq!(Some(Object::from(1)), index.remove("a"));
+        assert_eq!(Some(&Object::from(3)), index.get("a"));
+        assert_eq!(Some(&Object::from(2)), index.get("b"));
+    }
 }
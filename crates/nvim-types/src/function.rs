@@ -4,14 +4,16 @@ use std::ffi::c_int;
 use std::fmt;
 use std::marker::PhantomData;
 
-use luajit_bindings::{self as lua, ffi, Poppable, Pushable};
+use luajit_bindings::{self as lua, ffi, Poppable, Pushable, RegistryRef};
 
 use crate::{Error, LuaRef};
 
 /// A wrapper around a Lua reference to a function stored in the Lua registry.
+///
+/// The reference is released when the `Function` is dropped.
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Function<A, R> {
-    pub(crate) lua_ref: LuaRef,
+    pub(crate) lua_ref: RegistryRef,
     _pd: (PhantomData<A>, PhantomData<R>),
 }
 
@@ -20,7 +22,7 @@ impl<A, R> fmt::Debug for Function<A, R> {
         write!(
             f,
             "<function {}: {} -> {}>",
-            self.lua_ref,
+            self.lua_ref.as_raw(),
             std::any::type_name::<A>(),
             std::any::type_name::<R>()
         )
@@ -49,14 +51,13 @@ impl<A, R> Poppable for Function<A, R> {
 
         match ffi::lua_type(state, -1) {
             ffi::LUA_TFUNCTION => {
-                let lua_ref = ffi::luaL_ref(state, ffi::LUA_REGISTRYINDEX);
-                // TODO: check `lua_ref`.
-                Ok(Self::from_ref(lua_ref))
+                Ok(Self::from_ref(RegistryRef::from_stack(state)))
             },
 
-            other => Err(lua::Error::pop_wrong_type::<Self>(
+            _ => Err(lua::Error::pop_wrong_value::<Self>(
+                state,
+                -1,
                 ffi::LUA_TFUNCTION,
-                other,
             )),
         }
     }
@@ -67,19 +68,20 @@ impl<A, R> Pushable for Function<A, R> {
         self,
         state: *mut lua::ffi::lua_State,
     ) -> Result<c_int, lua::Error> {
-        ffi::lua_rawgeti(state, ffi::LUA_REGISTRYINDEX, self.lua_ref);
-        Ok(1)
+        self.lua_ref.push(state)
     }
 }
 
 impl<A, R> Function<A, R> {
-    pub(crate) fn from_ref(lua_ref: LuaRef) -> Self {
+    pub(crate) fn from_ref(lua_ref: RegistryRef) -> Self {
         Self { lua_ref, _pd: (PhantomData, PhantomData) }
     }
 
+    /// Returns the raw reference without giving up ownership, e.g. to pass
+    /// it to a Neovim function that doesn't take ownership of its arguments.
     #[doc(hidden)]
     pub fn lua_ref(&self) -> LuaRef {
-        self.lua_ref
+        self.lua_ref.as_raw()
     }
 
     pub fn from_fn<F, E>(fun: F) -> Self
@@ -130,19 +132,66 @@ impl<A, R> Function<A, R> {
         })
     }
 
+    /// Creates a function whose arguments borrow the strings passed by Lua
+    /// instead of copying them, e.g. a `Function<&str, usize>` created from
+    /// `|s: &str| Ok::<_, Error>(s.len())`. See
+    /// [`BorrowedArgs`](lua::BorrowedArgs) for the supported arguments.
+    pub fn from_fn_borrowed<F, E>(fun: F) -> Self
+    where
+        F: for<'a> Fn(<A as lua::BorrowedArgs<'a>>::Args) -> Result<R, E>
+            + 'static,
+        A: for<'a> lua::BorrowedArgs<'a>,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        Self::from_ref(lua::function::store_borrowed::<_, A, _, _>(fun))
+    }
+
+    /// Creates a function that can also yield back to whoever resumed the
+    /// coroutine it's called from. See [`Yielding`](lua::Yielding) for
+    /// details.
+    pub fn from_fn_yielding<F, Y, E>(fun: F) -> Self
+    where
+        F: Fn(A) -> Result<lua::Yielding<Y, R>, E> + 'static,
+        A: Poppable,
+        Y: Pushable,
+        R: Pushable,
+        E: StdError + 'static,
+    {
+        Self::from_ref(lua::function::store_yielding(fun))
+    }
+
     pub fn call(&self, args: A) -> Result<R, lua::Error>
     where
         A: Pushable,
         R: Poppable,
     {
-        lua::function::call(self.lua_ref, args)
+        lua::function::call(self.lua_ref.as_raw(), args)
     }
 
-    /// Consumes the `Function`, removing the reference stored in the Lua
-    /// registry.
-    #[doc(hidden)]
-    pub fn remove_from_lua_registry(self) {
-        lua::function::remove(self.lua_ref)
+    /// Same as [`call`](Function::call), but errors raised by the function
+    /// also include the Lua traceback.
+    pub fn call_with_traceback(&self, args: A) -> Result<R, lua::Error>
+    where
+        A: Pushable,
+        R: Poppable,
+    {
+        lua::function::call_with_traceback(self.lua_ref.as_raw(), args)
+    }
+
+    /// Same as [`call`](Function::call), but returns a
+    /// [`Timeout`](lua::Error::Timeout) error if the function runs past the
+    /// given budget. See [`lua::function::call_with_budget`] for details.
+    pub fn call_with_budget(
+        &self,
+        args: A,
+        budget: lua::hook::Budget,
+    ) -> Result<R, lua::Error>
+    where
+        A: Pushable,
+        R: Poppable,
+    {
+        lua::function::call_with_budget(self.lua_ref.as_raw(), args, budget)
     }
 }
 
@@ -154,6 +203,7 @@ mod serde {
     use serde::ser::{Serialize, Serializer};
 
     use super::Function;
+    use crate::object::clone_luaref;
     use crate::LuaRef;
 
     impl<A, R> Serialize for Function<A, R> {
@@ -161,7 +211,7 @@ mod serde {
         where
             S: Serializer,
         {
-            serializer.serialize_f32(self.lua_ref as f32)
+            serializer.serialize_f32(self.lua_ref.as_raw() as f32)
         }
     }
 
@@ -185,7 +235,7 @@ mod serde {
                 where
                     E: de::Error,
                 {
-                    Ok(Function::from_ref(value as LuaRef))
+                    Ok(Function::from_ref(clone_luaref(value as LuaRef)))
                 }
             }
 
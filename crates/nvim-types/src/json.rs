@@ -0,0 +1,271 @@
+//! Conversions between [`serde_json::Value`]s and Neovim [`Object`]s.
+//!
+//! The conversions are lossless in both directions, with a few choices
+//! matching the ones made by `vim.json`:
+//!
+//! - JSON `null`s are converted into nil objects, which are kept inside
+//!   arrays and dictionaries instead of being dropped (i.e. they behave like
+//!   `vim.NIL`);
+//! - JSON objects are always converted into dictionaries, even when they're
+//!   empty;
+//! - buffers, windows and tabpages are converted into their handles.
+//!
+//! JSON integers bigger than [`Integer::MAX`] are handled according to an
+//! [`IntegerOverflow`] policy.
+//!
+//! ```ignore
+//! use nvim_oxi::{json, Dictionary, Object};
+//! use serde_json::json;
+//!
+//! let obj = json::from_value(json!({ "foo": null, "bar": {} }))?;
+//! let dict = obj.as_dict().unwrap();
+//! assert!(dict.get("foo").unwrap().is_nil());
+//! assert_eq!(Some(&Dictionary::new()), dict.get("bar").unwrap().as_dict());
+//! ```
+
+use serde_json::{Map, Number, Value};
+use thiserror::Error as ThisError;
+
+use crate::conversion::{self, FromObject, ToObject};
+use crate::{
+    Array,
+    Dictionary,
+    Float,
+    Integer,
+    Object,
+    ObjectKind,
+    ObjectRef,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum Error {
+    #[error("Integer {0} is too big to be converted into an `Integer`")]
+    IntegerOverflow(u64),
+
+    #[error("Can't convert a NaN or infinite float into JSON")]
+    NonFiniteFloat,
+
+    #[error("Can't convert a \"{0}\" into JSON")]
+    UnsupportedKind(&'static str),
+
+    #[error(transparent)]
+    FromUtf8(#[from] std::str::Utf8Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What to do with JSON integers that don't fit in an [`Integer`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IntegerOverflow {
+    /// Fail with an [`Error::IntegerOverflow`].
+    #[default]
+    Error,
+
+    /// Convert the integer into a [`Float`], possibly losing precision.
+    Float,
+
+    /// Clamp the integer to [`Integer::MAX`].
+    Saturate,
+
+    /// Convert the integer into a string holding its decimal representation.
+    String,
+}
+
+/// Converts a JSON value into an [`Object`], failing on integers that don't
+/// fit in an [`Integer`].
+pub fn from_value(value: Value) -> Result<Object> {
+    from_value_with(value, IntegerOverflow::default())
+}
+
+/// Converts a JSON value into an [`Object`], handling integers that don't fit
+/// in an [`Integer`] according to `overflow`.
+pub fn from_value_with(
+    value: Value,
+    overflow: IntegerOverflow,
+) -> Result<Object> {
+    Ok(match value {
+        Value::Null => Object::nil(),
+
+        Value::Bool(b) => b.into(),
+
+        Value::Number(n) => from_number(&n, overflow)?,
+
+        Value::String(str) => str.into(),
+
+        Value::Array(values) => {
+            let mut array = Array::with_capacity(values.len());
+            for value in values {
+                array.push(from_value_with(value, overflow)?);
+            }
+            array.into()
+        },
+
+        Value::Object(map) => {
+            // Push the pairs directly instead of collecting them, which would
+            // drop the `null` values.
+            let mut dict = Dictionary::with_capacity(map.len());
+            for (key, value) in map {
+                dict.push((key, from_value_with(value, overflow)?).into());
+            }
+            dict.into()
+        },
+    })
+}
+
+/// Converts an [`Object`], an [`Array`] or a [`Dictionary`] into a JSON
+/// value.
+pub fn to_value<'a>(obj: impl Into<ObjectRef<'a>>) -> Result<Value> {
+    Ok(match obj.into() {
+        ObjectRef::Nil => Value::Null,
+
+        ObjectRef::Bool(b) => Value::Bool(b),
+
+        ObjectRef::Int(n)
+        | ObjectRef::Buffer(n)
+        | ObjectRef::Window(n)
+        | ObjectRef::TabPage(n) => Value::Number(n.into()),
+
+        ObjectRef::Float(n) => {
+            Value::Number(Number::from_f64(n).ok_or(Error::NonFiniteFloat)?)
+        },
+
+        ObjectRef::Str(str) => Value::String(str.as_str()?.to_owned()),
+
+        ObjectRef::Array(array) => {
+            Value::Array(array.iter().map(to_value).collect::<Result<_>>()?)
+        },
+
+        ObjectRef::Dict(dict) => {
+            let mut map = Map::new();
+            for (key, value) in dict {
+                map.insert(key.as_str()?.to_owned(), to_value(value)?);
+            }
+            Value::Object(map)
+        },
+
+        ObjectRef::LuaRef(_) => {
+            return Err(Error::UnsupportedKind(ObjectKind::LuaRef.as_static()))
+        },
+    })
+}
+
+fn from_number(n: &Number, overflow: IntegerOverflow) -> Result<Object> {
+    if let Some(n) = n.as_i64() {
+        return Ok(n.into());
+    }
+
+    if let Some(n) = n.as_u64() {
+        return match overflow {
+            IntegerOverflow::Error => Err(Error::IntegerOverflow(n)),
+            IntegerOverflow::Float => Ok((n as Float).into()),
+            IntegerOverflow::Saturate => Ok(Integer::MAX.into()),
+            IntegerOverflow::String => Ok(n.to_string().into()),
+        };
+    }
+
+    // If it's neither an `i64` nor a `u64` it has to be a float.
+    Ok(n.as_f64().unwrap_or(Float::NAN).into())
+}
+
+impl FromObject for Value {
+    fn from_object(
+        obj: Object,
+    ) -> std::result::Result<Self, conversion::Error> {
+        Ok(to_value(&obj)?)
+    }
+}
+
+impl ToObject for Value {
+    fn to_object(self) -> std::result::Result<Object, conversion::Error> {
+        Ok(from_value(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn value_roundtrip() {
+        let value = json!({
+            "null": null,
+            "bool": true,
+            "int": -42,
+            "float": 0.5,
+            "str": "foo",
+            "array": [1, null, "a"],
+            "empty_array": [],
+            "empty_object": {},
+        });
+
+        let obj = from_value(value.clone()).unwrap();
+        assert_eq!(value, to_value(&obj).unwrap());
+    }
+
+    #[test]
+    fn null_and_empty_object() {
+        let obj = from_value(json!({ "foo": null, "bar": {} })).unwrap();
+        let dict = obj.as_dict().unwrap();
+
+        assert_eq!(2, dict.len());
+        assert!(dict.get("foo").unwrap().is_nil());
+        assert_eq!(ObjectKind::Dictionary, dict.get("bar").unwrap().kind());
+
+        let array = from_value(json!([null, null])).unwrap();
+        assert_eq!(2, array.as_array().unwrap().len());
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let value = json!(u64::MAX);
+
+        assert_eq!(
+            Err(Error::IntegerOverflow(u64::MAX)),
+            from_value(value.clone())
+        );
+
+        assert_eq!(
+            Object::from(u64::MAX as Float),
+            from_value_with(value.clone(), IntegerOverflow::Float).unwrap()
+        );
+
+        assert_eq!(
+            Object::from(Integer::MAX),
+            from_value_with(value.clone(), IntegerOverflow::Saturate).unwrap()
+        );
+
+        assert_eq!(
+            Object::from(u64::MAX.to_string()),
+            from_value_with(value, IntegerOverflow::String).unwrap()
+        );
+    }
+
+    #[test]
+    fn to_value_errors() {
+        assert_eq!(
+            Err(Error::NonFiniteFloat),
+            to_value(&Object::from(Float::NAN))
+        );
+
+        let invalid = Object::from(crate::String::from_bytes(vec![0xff]));
+        assert!(matches!(to_value(&invalid), Err(Error::FromUtf8(_))));
+    }
+
+    #[test]
+    fn conversion_traits() {
+        assert_eq!(
+            json!({ "foo": [3] }),
+            Value::from_object(
+                Dictionary::from_iter([("foo", Array::from((3,)))]).into()
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            Object::from(Array::from((1, "a"))),
+            json!([1, "a"]).to_object().unwrap()
+        );
+    }
+}
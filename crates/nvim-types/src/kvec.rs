@@ -1,6 +1,7 @@
 //! This module contains functionality common to both `Array`s and
 //! `Dictionary`s.
 
+use std::mem::{self, ManuallyDrop};
 use std::ops::{Deref, DerefMut};
 use std::ptr;
 use std::slice;
@@ -11,6 +12,9 @@ use crate::NonOwning;
 //
 /// Binding to Klib's [`kvec`][1].
 ///
+/// The items are allocated with `malloc` and `realloc`, just like Neovim
+/// does, so a `KVec` can be freed by Neovim with `xfree` and vice versa.
+///
 /// [1]: https://github.com/attractivechaos/klib/blob/master/kvec.h
 #[repr(C)]
 pub struct KVec<T> {
@@ -29,6 +33,9 @@ impl<T> Default for KVec<T> {
     }
 }
 
+// `insert`, `remove` and `retain` are called `insert_at`, `remove_at` and
+// `retain_mut` because `Dictionary` uses the former names for its map API.
+// `Array` re-exposes them under the `Vec` names.
 impl<T> KVec<T> {
     /// Creates a new empty `Collection`.
     #[inline]
@@ -36,6 +43,15 @@ impl<T> KVec<T> {
         Self { items: std::ptr::null_mut(), size: 0, capacity: 0 }
     }
 
+    /// Creates a new empty `Collection` with space for at least `capacity`
+    /// items.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut kvec = Self::new();
+        kvec.reserve_exact(capacity);
+        kvec
+    }
+
     /// The number of items in the collection.
     #[inline]
     pub const fn len(&self) -> usize {
@@ -47,8 +63,14 @@ impl<T> KVec<T> {
         self.len() == 0
     }
 
+    /// The number of items the collection can hold without reallocating.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     #[inline]
-    pub(crate) fn as_slice(&self) -> &[T] {
+    pub fn as_slice(&self) -> &[T] {
         if self.items.is_null() {
             &[]
         } else {
@@ -57,17 +79,193 @@ impl<T> KVec<T> {
     }
 
     #[inline]
-    pub(crate) fn as_mut_slice(&mut self) -> &mut [T] {
-        unsafe { slice::from_raw_parts_mut(self.items, self.size) }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        if self.items.is_null() {
+            &mut []
+        } else {
+            unsafe { slice::from_raw_parts_mut(self.items, self.size) }
+        }
+    }
+
+    /// Reserves space for at least `additional` more items, growing the
+    /// capacity geometrically like `kv_push` does.
+    pub fn reserve(&mut self, additional: usize) {
+        let required =
+            self.size.checked_add(additional).expect("capacity overflow");
+
+        if required > self.capacity {
+            self.grow_to(required.max(self.capacity * 2).max(4));
+        }
+    }
+
+    /// Reserves space for exactly `additional` more items.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let required =
+            self.size.checked_add(additional).expect("capacity overflow");
+
+        if required > self.capacity {
+            self.grow_to(required);
+        }
+    }
+
+    /// Shrinks the capacity of the collection to its length.
+    pub fn shrink_to_fit(&mut self) {
+        if self.capacity == self.size {
+            return;
+        }
+
+        if self.size == 0 {
+            unsafe { free(self.items) };
+            self.items = ptr::null_mut();
+            self.capacity = 0;
+        } else {
+            self.realloc(self.size);
+        }
+    }
+
+    /// Appends an item to the end of the collection.
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        if self.size == self.capacity {
+            self.reserve(1);
+        }
+        unsafe { ptr::write(self.items.add(self.size), item) };
+        self.size += 1;
+    }
+
+    /// Removes the last item from the collection and returns it, or `None`
+    /// if it's empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        Some(unsafe { ptr::read(self.items.add(self.size)) })
+    }
+
+    /// Inserts an item at position `idx`, shifting all the items after it
+    /// to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx > len`.
+    pub fn insert_at(&mut self, idx: usize, item: T) {
+        assert!(
+            idx <= self.size,
+            "insertion index (is {idx}) should be <= len (is {})",
+            self.size
+        );
+
+        if self.size == self.capacity {
+            self.reserve(1);
+        }
+
+        unsafe {
+            let ptr = self.items.add(idx);
+            ptr::copy(ptr, ptr.add(1), self.size - idx);
+            ptr::write(ptr, item);
+        }
+
+        self.size += 1;
+    }
+
+    /// Removes and returns the item at position `idx`, shifting all the
+    /// items after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn remove_at(&mut self, idx: usize) -> T {
+        assert!(
+            idx < self.size,
+            "removal index (is {idx}) should be < len (is {})",
+            self.size
+        );
+
+        unsafe {
+            let ptr = self.items.add(idx);
+            let item = ptr::read(ptr);
+            ptr::copy(ptr.add(1), ptr, self.size - idx - 1);
+            self.size -= 1;
+            item
+        }
+    }
+
+    /// Removes and returns the item at position `idx`, replacing it with
+    /// the last item of the collection. This doesn't preserve the order of
+    /// the items, but it's O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn swap_remove(&mut self, idx: usize) -> T {
+        assert!(
+            idx < self.size,
+            "swap_remove index (is {idx}) should be < len (is {})",
+            self.size
+        );
+
+        let last = self.size - 1;
+        self.as_mut_slice().swap(idx, last);
+        self.pop().unwrap()
     }
 
+    /// Shortens the collection to its first `len` items, dropping the rest.
+    /// Does nothing if `len` is greater than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.size {
+            return;
+        }
+
+        let tail = ptr::slice_from_raw_parts_mut(
+            unsafe { self.items.add(len) },
+            self.size - len,
+        );
+
+        // Update the length first, so that the items can't be dropped twice
+        // if one of their destructors panics.
+        self.size = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    /// Removes all the items from the collection, keeping its capacity.
     #[inline]
-    pub(crate) unsafe fn from_raw_parts(
-        ptr: *mut T,
-        size: usize,
-        capacity: usize,
-    ) -> Self {
-        Self { items: ptr, size, capacity }
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Retains only the items for which `fun` returns `true`, preserving
+    /// their order.
+    pub fn retain_mut<F>(&mut self, mut fun: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let len = self.size;
+
+        // Items are moved out one at a time, so if `fun` panics we leak the
+        // ones that haven't been looked at yet instead of dropping them
+        // twice.
+        self.size = 0;
+
+        let mut kept = 0;
+
+        for idx in 0..len {
+            unsafe {
+                let ptr = self.items.add(idx);
+                if fun(&mut *ptr) {
+                    if idx != kept {
+                        ptr::copy_nonoverlapping(ptr, self.items.add(kept), 1);
+                    }
+                    kept += 1;
+                    self.size = kept;
+                } else {
+                    ptr::drop_in_place(ptr);
+                }
+            }
+        }
+
+        self.size = kept;
     }
 
     /// Make a non-owning version of this `Collection`.
@@ -76,20 +274,56 @@ impl<T> KVec<T> {
     pub fn non_owning(&self) -> NonOwning<'_, Self> {
         NonOwning::new(Self { ..*self })
     }
+
+    /// Reallocates the items to hold at least `capacity` of them.
+    fn grow_to(&mut self, capacity: usize) {
+        debug_assert!(capacity > self.capacity);
+        self.realloc(capacity);
+    }
+
+    fn realloc(&mut self, capacity: usize) {
+        let size = mem::size_of::<T>()
+            .checked_mul(capacity)
+            .expect("capacity overflow");
+
+        // Zero-sized types don't need any memory, but we still allocate one
+        // byte so that the pointer is valid and can be freed.
+        let ptr = unsafe {
+            libc::realloc(self.items as *mut libc::c_void, size.max(1))
+        };
+
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(
+                std::alloc::Layout::array::<T>(capacity).unwrap(),
+            );
+        }
+
+        self.items = ptr as *mut T;
+        self.capacity = capacity;
+    }
+}
+
+/// Frees a buffer allocated by a [`KVec`], which can be null.
+#[inline]
+pub(crate) unsafe fn free<T>(items: *mut T) {
+    if !items.is_null() {
+        libc::free(items as *mut libc::c_void);
+    }
 }
 
 impl<T: Clone> Clone for KVec<T> {
     fn clone(&self) -> Self {
-        self.as_slice().to_owned().into()
+        let mut kvec = Self::with_capacity(self.size);
+        kvec.extend(self.iter().cloned());
+        kvec
     }
 }
 
 impl<T> Drop for KVec<T> {
     fn drop(&mut self) {
         unsafe {
-            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
-                self.items, self.size,
-            ))
+            ptr::drop_in_place(self.as_mut_slice());
+            free(self.items);
         }
     }
 }
@@ -115,26 +349,109 @@ impl<T> DerefMut for KVec<T> {
     }
 }
 
+impl<T> Extend<T> for KVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for KVec<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
 impl<T> From<Vec<T>> for KVec<T> {
     #[inline]
     fn from(vec: Vec<T>) -> Self {
-        let size = vec.len();
-        let capacity = vec.capacity();
-        let ptr = vec.leak() as *mut [T] as *mut T;
+        // The `Vec` was allocated by Rust's allocator, so the items have to
+        // be moved to a buffer that Neovim can free.
+        let mut vec = ManuallyDrop::new(vec);
+        let mut kvec = Self::with_capacity(vec.len());
+
+        unsafe {
+            ptr::copy_nonoverlapping(vec.as_ptr(), kvec.items, vec.len());
+            kvec.size = vec.len();
+            vec.set_len(0);
+            ManuallyDrop::drop(&mut vec);
+        }
 
-        unsafe { Self::from_raw_parts(ptr, size, capacity) }
+        kvec
     }
 }
 
 impl<T> From<KVec<T>> for Vec<T> {
     #[inline]
-    fn from(coll: KVec<T>) -> Self {
+    fn from(kvec: KVec<T>) -> Self {
+        // The items are moved to the `Vec`, so we can't let `kvec` drop them.
+        let kvec = ManuallyDrop::new(kvec);
+        let mut vec = Vec::with_capacity(kvec.size);
+
         unsafe {
-            if coll.items.is_null() {
-                Vec::new()
-            } else {
-                Vec::from_raw_parts(coll.items, coll.size, coll.capacity)
-            }
+            ptr::copy_nonoverlapping(kvec.items, vec.as_mut_ptr(), kvec.size);
+            vec.set_len(kvec.size);
+            free(kvec.items);
         }
+
+        vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_pop() {
+        let mut kvec = KVec::new();
+        for i in 0..100 {
+            kvec.push(i);
+        }
+        assert_eq!(100, kvec.len());
+        assert!(kvec.capacity() >= 100);
+        assert_eq!(Some(99), kvec.pop());
+        assert_eq!((0..99).collect::<Vec<_>>(), &*kvec);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut kvec = KVec::from(vec![1, 2, 4]);
+        kvec.insert_at(2, 3);
+        kvec.insert_at(4, 5);
+        kvec.insert_at(0, 0);
+        assert_eq!([0, 1, 2, 3, 4, 5], *kvec);
+        assert_eq!(0, kvec.remove_at(0));
+        assert_eq!(5, kvec.remove_at(4));
+        assert_eq!(2, kvec.swap_remove(1));
+        assert_eq!([1, 4, 3], *kvec);
+    }
+
+    #[test]
+    fn truncate_retain() {
+        let strings = (0..10).map(|n| n.to_string()).collect::<Vec<_>>();
+        let mut kvec = KVec::from(strings);
+        kvec.retain_mut(|s| s.parse::<u8>().unwrap() % 2 == 0);
+        assert_eq!(["0", "2", "4", "6", "8"], *kvec);
+        kvec.truncate(2);
+        assert_eq!(["0", "2"], *kvec);
+        kvec.clear();
+        assert!(kvec.is_empty());
+        kvec.shrink_to_fit();
+        assert_eq!(0, kvec.capacity());
+    }
+
+    #[test]
+    fn extend_sort_vec() {
+        let mut kvec = KVec::new();
+        kvec.extend([3, 1, 2]);
+        kvec.extend(&[0]);
+        kvec.sort();
+        assert_eq!([0, 1, 2], kvec[..3]);
+        assert_eq!(vec![0, 1, 2, 3], Vec::from(kvec.clone()));
+        assert!(kvec == KVec::from(vec![0, 1, 2, 3]));
     }
 }
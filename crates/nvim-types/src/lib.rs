@@ -6,7 +6,11 @@ pub mod conversion;
 mod dictionary;
 mod error;
 mod function;
+#[cfg(feature = "json")]
+pub mod json;
 mod kvec;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
 mod non_owning;
 mod object;
 #[cfg(feature = "serde")]
@@ -14,13 +18,27 @@ pub mod serde;
 mod string;
 
 pub use array::{Array, ArrayIterator};
-pub use dictionary::{DictIterator, Dictionary, KeyValuePair};
+pub use dictionary::{
+    DictIndex,
+    DictIter,
+    DictIterMut,
+    DictIterator,
+    Dictionary,
+    Entry,
+    KeyValuePair,
+    OccupiedEntry,
+    VacantEntry,
+};
 pub use error::Error;
 pub use function::Function;
 pub use kvec::KVec;
 pub use non_owning::NonOwning;
-pub use object::{Object, ObjectKind};
-pub use string::String;
+pub use object::{Object, ObjectKind, ObjectRef};
+pub use string::{String, StringChars};
+
+/// Any number of values of any type, e.g. to return a mix of strings and
+/// numbers from a Rust function, or nothing at all.
+pub type MultiValue = luajit_bindings::Variadic<Object>;
 
 // https://github.com/neovim/neovim/blob/master/src/nvim/api/private/defs.h#L67
 #[doc(hidden)]
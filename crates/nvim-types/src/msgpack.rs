@@ -0,0 +1,531 @@
+//! Encoding and decoding of Neovim [`Object`]s to and from [MessagePack][1],
+//! the format used by Neovim's RPC API.
+//!
+//! Objects are encoded the same way Neovim encodes them on the wire. In
+//! particular buffers, windows and tabpages are encoded as the EXT types
+//! listed under `types` by `nvim --api-info`, with their handle as payload.
+//!
+//! ```ignore
+//! use nvim_oxi::{msgpack, Dictionary};
+//!
+//! let dict = Dictionary::from_iter([("foo", 1)]);
+//! let bytes = msgpack::to_vec(&dict)?;
+//! assert_eq!(dict, msgpack::from_slice::<Dictionary>(&bytes)?);
+//! ```
+//!
+//! [1]: https://msgpack.org
+
+use std::io::{self, Read, Write};
+
+use rmp::encode::{self, ValueWriteError};
+use rmp::Marker;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use crate::conversion::{self, FromObject};
+use crate::serde::{Deserializer, Serializer};
+use crate::{Array, Dictionary, Integer, Object, ObjectKind, ObjectRef};
+
+// The `id`s of the `types` listed by `nvim --api-info`.
+const EXT_BUFFER: i8 = 0;
+const EXT_WINDOW: i8 = 1;
+const EXT_TABPAGE: i8 = 2;
+
+/// The maximum number of items preallocated when decoding an array or a
+/// map, so that a bogus length can't make us allocate a huge buffer.
+const MAX_PREALLOC: usize = 1024;
+
+/// The maximum nesting depth of the decoded objects, so that deeply nested
+/// arrays or maps can't overflow the stack.
+const MAX_DEPTH: usize = 1024;
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("Can't encode a \"{0}\" as MessagePack")]
+    UnsupportedKind(&'static str),
+
+    #[error("Can't decode the reserved MessagePack marker")]
+    ReservedMarker,
+
+    #[error("Integer {0} is too big to be decoded as an `Integer`")]
+    IntegerOverflow(u64),
+
+    #[error("Dictionary keys must be strings, found {0:?} instead")]
+    InvalidKey(Marker),
+
+    #[error("Unknown extension type {0}")]
+    UnknownExt(i8),
+
+    #[error("Found {0} trailing bytes after the encoded object")]
+    TrailingBytes(usize),
+
+    #[error("Objects can't be nested more than {MAX_DEPTH} levels deep")]
+    DepthLimitExceeded,
+
+    #[error(transparent)]
+    Conversion(#[from] conversion::Error),
+
+    #[error(transparent)]
+    Serde(#[from] crate::serde::Error),
+}
+
+impl From<ValueWriteError> for Error {
+    #[inline]
+    fn from(err: ValueWriteError) -> Self {
+        Self::Io(err.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Encodes an [`Object`], an [`Array`] or a [`Dictionary`] into `writer`.
+pub fn encode<'a, W>(
+    value: impl Into<ObjectRef<'a>>,
+    writer: &mut W,
+) -> Result<()>
+where
+    W: Write,
+{
+    write_object(writer, value.into())
+}
+
+/// Encodes an [`Object`], an [`Array`] or a [`Dictionary`] into a vector of
+/// bytes.
+pub fn to_vec<'a>(value: impl Into<ObjectRef<'a>>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    encode(value, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes a single object from `reader`, converting it into a `T`. The
+/// reader is left right after the end of the object, so it can be called
+/// repeatedly to decode a stream of objects.
+///
+/// Arrays and maps nested more than 1024 levels deep are rejected with an
+/// [`Error::DepthLimitExceeded`].
+pub fn decode<T, R>(reader: &mut R) -> Result<T>
+where
+    T: FromObject,
+    R: Read,
+{
+    Ok(T::from_object(read_object(reader, 0)?)?)
+}
+
+/// Decodes the object encoded in `bytes`, converting it into a `T`.
+pub fn from_slice<T: FromObject>(mut bytes: &[u8]) -> Result<T> {
+    let value = decode(&mut bytes)?;
+    match bytes.len() {
+        0 => Ok(value),
+        n => Err(Error::TrailingBytes(n)),
+    }
+}
+
+/// Serializes any value implementing [`serde::Serialize`], using the same
+/// representation as [`Serializer`].
+pub fn serialize<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    to_vec(&value.serialize(Serializer::new())?)
+}
+
+/// Deserializes any value implementing [`serde::Deserialize`], using the same
+/// representation as [`Deserializer`].
+pub fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let obj = from_slice::<Object>(bytes)?;
+    Ok(T::deserialize(Deserializer::new(obj))?)
+}
+
+fn write_object<W: Write>(wr: &mut W, obj: ObjectRef<'_>) -> Result<()> {
+    match obj {
+        ObjectRef::Nil => encode::write_nil(wr)?,
+
+        ObjectRef::Bool(b) => encode::write_bool(wr, b)?,
+
+        ObjectRef::Int(n) => {
+            encode::write_sint(wr, n)?;
+        },
+
+        ObjectRef::Float(n) => encode::write_f64(wr, n)?,
+
+        ObjectRef::Str(str) => {
+            // Neovim strings aren't necessarily valid UTF-8, so we can't use
+            // `write_str`.
+            encode::write_str_len(wr, len_u32(str.len())?)?;
+            wr.write_all(str.as_bytes())?;
+        },
+
+        ObjectRef::Array(array) => {
+            encode::write_array_len(wr, len_u32(array.len())?)?;
+            for obj in array {
+                write_object(wr, obj.into())?;
+            }
+        },
+
+        ObjectRef::Dict(dict) => {
+            encode::write_map_len(wr, len_u32(dict.len())?)?;
+            for (key, value) in dict {
+                write_object(wr, ObjectRef::Str(key))?;
+                write_object(wr, value.into())?;
+            }
+        },
+
+        ObjectRef::LuaRef(_) => {
+            return Err(Error::UnsupportedKind(ObjectKind::LuaRef.as_static()))
+        },
+
+        ObjectRef::Buffer(handle) => write_handle(wr, EXT_BUFFER, handle)?,
+        ObjectRef::Window(handle) => write_handle(wr, EXT_WINDOW, handle)?,
+        ObjectRef::TabPage(handle) => write_handle(wr, EXT_TABPAGE, handle)?,
+    }
+
+    Ok(())
+}
+
+/// Writes a buffer, window or tabpage handle as an EXT whose payload is the
+/// handle encoded as an integer, like Neovim does.
+fn write_handle<W: Write>(wr: &mut W, ty: i8, handle: Integer) -> Result<()> {
+    let mut payload = Vec::with_capacity(9);
+    encode::write_sint(&mut payload, handle)?;
+    encode::write_ext_meta(wr, payload.len() as u32, ty)?;
+    wr.write_all(&payload)?;
+    Ok(())
+}
+
+fn len_u32(len: usize) -> Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "MessagePack collections can't hold more than 2^32 - 1 items",
+        )
+        .into()
+    })
+}
+
+/// Reads an object nested `depth` levels deep.
+fn read_object<R: Read>(rd: &mut R, depth: usize) -> Result<Object> {
+    if depth > MAX_DEPTH {
+        return Err(Error::DepthLimitExceeded);
+    }
+
+    // Every kind of value is read by a separate function to keep the stack
+    // frames of the recursion small.
+    match read_marker(rd)? {
+        marker @ (Marker::FixArray(_) | Marker::Array16 | Marker::Array32) => {
+            read_array(rd, marker, depth)
+        },
+
+        marker @ (Marker::FixMap(_) | Marker::Map16 | Marker::Map32) => {
+            read_map(rd, marker, depth)
+        },
+
+        marker @ (Marker::FixExt1
+        | Marker::FixExt2
+        | Marker::FixExt4
+        | Marker::FixExt8
+        | Marker::FixExt16
+        | Marker::Ext8
+        | Marker::Ext16
+        | Marker::Ext32) => read_handle(rd, marker, depth),
+
+        marker => read_scalar(rd, marker),
+    }
+}
+
+/// Reads an array whose elements are nested `depth + 1` levels deep.
+fn read_array<R: Read>(
+    rd: &mut R,
+    marker: Marker,
+    depth: usize,
+) -> Result<Object> {
+    let len = read_len(rd, marker)?;
+    let mut array = Array::with_capacity(len.min(MAX_PREALLOC));
+    for _ in 0..len {
+        array.push(read_object(rd, depth + 1)?);
+    }
+    Ok(array.into())
+}
+
+/// Reads a map whose values are nested `depth + 1` levels deep.
+fn read_map<R: Read>(
+    rd: &mut R,
+    marker: Marker,
+    depth: usize,
+) -> Result<Object> {
+    let len = read_len(rd, marker)?;
+    let mut dict = Dictionary::with_capacity(len.min(MAX_PREALLOC));
+    for _ in 0..len {
+        let key = read_key(rd)?;
+        let value = read_object(rd, depth + 1)?;
+        dict.push((key, value).into());
+    }
+    Ok(dict.into())
+}
+
+/// Reads a value that's neither a container nor an EXT.
+fn read_scalar<R: Read>(rd: &mut R, marker: Marker) -> Result<Object> {
+    Ok(match marker {
+        Marker::Null => Object::nil(),
+        Marker::True => true.into(),
+        Marker::False => false.into(),
+
+        Marker::FixPos(n) => Integer::from(n).into(),
+        Marker::FixNeg(n) => Integer::from(n).into(),
+        Marker::U8 => Integer::from(read_u8(rd)?).into(),
+        Marker::U16 => {
+            Integer::from(u16::from_be_bytes(read_bytes(rd)?)).into()
+        },
+        Marker::U32 => {
+            Integer::from(u32::from_be_bytes(read_bytes(rd)?)).into()
+        },
+        Marker::U64 => {
+            let n = u64::from_be_bytes(read_bytes(rd)?);
+            Integer::try_from(n).map_err(|_| Error::IntegerOverflow(n))?.into()
+        },
+        Marker::I8 => Integer::from(read_u8(rd)? as i8).into(),
+        Marker::I16 => {
+            Integer::from(i16::from_be_bytes(read_bytes(rd)?)).into()
+        },
+        Marker::I32 => {
+            Integer::from(i32::from_be_bytes(read_bytes(rd)?)).into()
+        },
+        Marker::I64 => i64::from_be_bytes(read_bytes(rd)?).into(),
+
+        Marker::F32 => f64::from(f32::from_be_bytes(read_bytes(rd)?)).into(),
+        Marker::F64 => f64::from_be_bytes(read_bytes(rd)?).into(),
+
+        // Neovim decodes both strings and binary blobs into strings.
+        Marker::FixStr(_)
+        | Marker::Str8
+        | Marker::Str16
+        | Marker::Str32
+        | Marker::Bin8
+        | Marker::Bin16
+        | Marker::Bin32 => read_string(rd, marker)?.into(),
+
+        Marker::Reserved => return Err(Error::ReservedMarker),
+
+        // Containers and EXTs are handled by `read_object`.
+        _ => unreachable!(),
+    })
+}
+
+/// Reads the key of a map, which has to be a string.
+fn read_key<R: Read>(rd: &mut R) -> Result<crate::String> {
+    match read_marker(rd)? {
+        marker @ (Marker::FixStr(_)
+        | Marker::Str8
+        | Marker::Str16
+        | Marker::Str32
+        | Marker::Bin8
+        | Marker::Bin16
+        | Marker::Bin32) => read_string(rd, marker),
+        other => Err(Error::InvalidKey(other)),
+    }
+}
+
+/// Reads a buffer, window or tabpage handle encoded as an EXT.
+fn read_handle<R: Read>(
+    rd: &mut R,
+    marker: Marker,
+    depth: usize,
+) -> Result<Object> {
+    let len = match marker {
+        Marker::FixExt1 => 1,
+        Marker::FixExt2 => 2,
+        Marker::FixExt4 => 4,
+        Marker::FixExt8 => 8,
+        Marker::FixExt16 => 16,
+        Marker::Ext8 => read_u8(rd)? as usize,
+        Marker::Ext16 => u16::from_be_bytes(read_bytes(rd)?) as usize,
+        _ => u32::from_be_bytes(read_bytes(rd)?) as usize,
+    };
+
+    let ty = read_u8(rd)? as i8;
+
+    let kind = match ty {
+        EXT_BUFFER => ObjectKind::Buffer,
+        EXT_WINDOW => ObjectKind::Window,
+        EXT_TABPAGE => ObjectKind::TabPage,
+        other => return Err(Error::UnknownExt(other)),
+    };
+
+    // Any byte left in the payload after the handle is ignored, like Neovim
+    // does.
+    let payload = read_vec(rd, len)?;
+    let handle = Integer::from_object(read_object(
+        &mut payload.as_slice(),
+        depth + 1,
+    )?)?;
+
+    Ok(Object::from_handle(kind, handle))
+}
+
+fn read_string<R: Read>(rd: &mut R, marker: Marker) -> Result<crate::String> {
+    let len = read_len(rd, marker)?;
+    Ok(crate::String::from_bytes(read_vec(rd, len)?))
+}
+
+/// Reads exactly `len` bytes.
+fn read_vec<R: Read>(rd: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOC));
+    rd.take(len as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(bytes)
+}
+
+/// Reads the length of a string, a binary blob, an array or a map.
+fn read_len<R: Read>(rd: &mut R, marker: Marker) -> Result<usize> {
+    Ok(match marker {
+        Marker::FixStr(len) | Marker::FixArray(len) | Marker::FixMap(len) => {
+            len as usize
+        },
+        Marker::Str8 | Marker::Bin8 => read_u8(rd)? as usize,
+        Marker::Str16 | Marker::Bin16 | Marker::Array16 | Marker::Map16 => {
+            u16::from_be_bytes(read_bytes(rd)?) as usize
+        },
+        _ => u32::from_be_bytes(read_bytes(rd)?) as usize,
+    })
+}
+
+fn read_marker<R: Read>(rd: &mut R) -> Result<Marker> {
+    Ok(Marker::from_u8(read_u8(rd)?))
+}
+
+fn read_u8<R: Read>(rd: &mut R) -> Result<u8> {
+    Ok(read_bytes::<R, 1>(rd)?[0])
+}
+
+fn read_bytes<R: Read, const N: usize>(rd: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    rd.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn object_roundtrip() {
+        let obj = Object::from(Dictionary::from_iter([
+            ("nil", Object::nil()),
+            ("bool", Object::from(true)),
+            ("int", Object::from(-1_000_000)),
+            ("float", Object::from(0.5)),
+            ("str", Object::from("foo")),
+            ("array", Array::from((1, u32::MAX, i64::MIN)).into()),
+        ]));
+
+        let bytes = to_vec(&obj).unwrap();
+        assert_eq!(obj, from_slice::<Object>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn encode_like_neovim() {
+        let array = Array::from((1, "a", true));
+        assert_eq!(
+            b"\x93\x01\xa1a\xc3".as_slice(),
+            to_vec(&array).unwrap().as_slice()
+        );
+
+        let buffer = Object::from_handle(ObjectKind::Buffer, 3);
+        assert_eq!(
+            b"\xd4\x00\x03".as_slice(),
+            to_vec(&buffer).unwrap().as_slice()
+        );
+
+        let window = Object::from_handle(ObjectKind::Window, 1000);
+        let bytes = to_vec(&window).unwrap();
+        assert_eq!(b"\xc7\x03\x01\xcd\x03\xe8".as_slice(), bytes.as_slice());
+        assert_eq!(window, from_slice::<Object>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(
+            from_slice::<Object>(b"\xcf\xff\xff\xff\xff\xff\xff\xff\xff"),
+            Err(Error::IntegerOverflow(u64::MAX))
+        ));
+
+        assert!(matches!(
+            from_slice::<Object>(b"\x81\x01\x02"),
+            Err(Error::InvalidKey(Marker::FixPos(1)))
+        ));
+
+        assert!(matches!(
+            from_slice::<Object>(b"\xd4\x05\x03"),
+            Err(Error::UnknownExt(5))
+        ));
+
+        assert!(matches!(
+            from_slice::<Object>(b"\xc0\xc0"),
+            Err(Error::TrailingBytes(1))
+        ));
+
+        assert!(matches!(
+            from_slice::<Object>(b"\xa3fo"),
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        assert!(matches!(
+            from_slice::<Array>(b"\xc0"),
+            Err(Error::Conversion(_))
+        ));
+    }
+
+    #[test]
+    fn decode_depth_limit() {
+        let mut nested = vec![0x91; MAX_DEPTH];
+        nested.push(0xc0);
+        assert!(from_slice::<Object>(&nested).is_ok());
+
+        let too_nested = vec![0x91; 100_000];
+        assert!(matches!(
+            from_slice::<Object>(&too_nested),
+            Err(Error::DepthLimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn decode_stream() {
+        let mut bytes = to_vec(&Object::from(1)).unwrap();
+        bytes.extend(to_vec(&Object::from("two")).unwrap());
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(1, decode::<u8, _>(&mut reader).unwrap());
+        assert_eq!("two", decode::<String, _>(&mut reader).unwrap());
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            name: String,
+            sizes: Vec<u32>,
+            extra: BTreeMap<String, bool>,
+        }
+
+        let config = Config {
+            name: "foo".into(),
+            sizes: vec![1, 2],
+            extra: BTreeMap::from([("bar".into(), true)]),
+        };
+
+        let bytes = serialize(&config).unwrap();
+        assert_eq!(config, deserialize::<Config>(&bytes).unwrap());
+    }
+}
@@ -3,7 +3,7 @@ use std::ffi::c_int;
 use std::fmt;
 use std::mem::ManuallyDrop;
 
-use lua::{ffi::*, Poppable, Pushable};
+use lua::{ffi::*, Poppable, Pushable, RegistryRef};
 use luajit_bindings as lua;
 
 use crate::{
@@ -65,6 +65,23 @@ impl ObjectKind {
     }
 }
 
+/// A borrowed view of an [`Object`], returned by [`Object::view`], which can
+/// be pattern matched on without cloning or consuming the object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ObjectRef<'a> {
+    Nil,
+    Bool(Boolean),
+    Int(Integer),
+    Float(Float),
+    Str(&'a crate::String),
+    Array(&'a Array),
+    Dict(&'a Dictionary),
+    LuaRef(LuaRef),
+    Buffer(Integer),
+    Window(Integer),
+    TabPage(Integer),
+}
+
 // https://github.com/neovim/neovim/blob/master/src/nvim/api/private/defs.h#L111
 #[repr(C)]
 union ObjectData {
@@ -124,9 +141,25 @@ impl Object {
         !self.is_nil()
     }
 
+    /// Creates a new object from a reference to a Lua function, which will
+    /// be released when the object is dropped.
     #[inline(always)]
-    pub fn from_luaref(luaref: LuaRef) -> Self {
-        Self { ty: ObjectKind::LuaRef, data: ObjectData { luaref } }
+    pub fn from_luaref(luaref: RegistryRef) -> Self {
+        Self {
+            ty: ObjectKind::LuaRef,
+            data: ObjectData { luaref: luaref.into_raw() },
+        }
+    }
+
+    /// Creates a new buffer, window or tabpage object from its handle.
+    #[cfg(feature = "msgpack")]
+    #[inline]
+    pub(crate) fn from_handle(kind: ObjectKind, handle: Integer) -> Self {
+        debug_assert!(matches!(
+            kind,
+            ObjectKind::Buffer | ObjectKind::Window | ObjectKind::TabPage
+        ));
+        Self { ty: kind, data: ObjectData { integer: handle } }
     }
 
     #[inline]
@@ -162,6 +195,13 @@ impl Object {
         self.data.luaref
     }
 
+    /// Extracts the contained reference to a Lua function without checking
+    /// that the object actually contains one.
+    pub unsafe fn into_luaref_unchecked(self) -> RegistryRef {
+        let luaref = ManuallyDrop::new(self);
+        RegistryRef::from_raw(luaref.data.luaref)
+    }
+
     /// Extracts the contained [`String`](crate::String) value without checking
     /// that the object actually contains a [`String`](crate::String).
     pub unsafe fn into_string_unchecked(self) -> crate::String {
@@ -184,6 +224,256 @@ impl Object {
     }
 }
 
+impl Object {
+    /// Returns a borrowed view of the object that can be pattern matched on.
+    pub fn view(&self) -> ObjectRef<'_> {
+        use ObjectKind::*;
+        unsafe {
+            match self.ty {
+                Nil => ObjectRef::Nil,
+                Boolean => ObjectRef::Bool(self.data.boolean),
+                Integer => ObjectRef::Int(self.data.integer),
+                Float => ObjectRef::Float(self.data.float),
+                String => ObjectRef::Str(&self.data.string),
+                Array => ObjectRef::Array(&self.data.array),
+                Dictionary => ObjectRef::Dict(&self.data.dictionary),
+                LuaRef => ObjectRef::LuaRef(self.data.luaref),
+                Buffer => ObjectRef::Buffer(self.data.integer),
+                Window => ObjectRef::Window(self.data.integer),
+                TabPage => ObjectRef::TabPage(self.data.integer),
+            }
+        }
+    }
+
+    /// Returns the contained boolean, if the object is one.
+    #[inline]
+    pub fn as_boolean(&self) -> Option<Boolean> {
+        if self.ty == ObjectKind::Boolean {
+            Some(unsafe { self.data.boolean })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the contained integer, if the object is one. Buffers, windows
+    /// and tabpages are returned as their handles.
+    #[inline]
+    pub fn as_integer(&self) -> Option<Integer> {
+        if self.is_integer() {
+            Some(unsafe { self.data.integer })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the contained float, if the object is one.
+    #[inline]
+    pub fn as_float(&self) -> Option<Float> {
+        if self.ty == ObjectKind::Float {
+            Some(unsafe { self.data.float })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the contained Lua reference, if the object is one.
+    #[inline]
+    pub fn as_luaref(&self) -> Option<LuaRef> {
+        if self.ty == ObjectKind::LuaRef {
+            Some(unsafe { self.data.luaref })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the contained [`String`](crate::String), if
+    /// the object is one.
+    #[inline]
+    pub fn as_string(&self) -> Option<&crate::String> {
+        if self.ty == ObjectKind::String {
+            Some(unsafe { &*self.data.string })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the contained [`Array`], if the object is one.
+    #[inline]
+    pub fn as_array(&self) -> Option<&Array> {
+        if self.ty == ObjectKind::Array {
+            Some(unsafe { &*self.data.array })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the contained [`Dictionary`], if the object is
+    /// one.
+    #[inline]
+    pub fn as_dict(&self) -> Option<&Dictionary> {
+        if self.ty == ObjectKind::Dictionary {
+            Some(unsafe { &*self.data.dictionary })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained boolean, if the object is
+    /// one.
+    #[inline]
+    pub fn as_boolean_mut(&mut self) -> Option<&mut Boolean> {
+        if self.ty == ObjectKind::Boolean {
+            Some(unsafe { &mut self.data.boolean })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained integer, if the object
+    /// is one.
+    #[inline]
+    pub fn as_integer_mut(&mut self) -> Option<&mut Integer> {
+        if self.is_integer() {
+            Some(unsafe { &mut self.data.integer })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained float, if the object is
+    /// one.
+    #[inline]
+    pub fn as_float_mut(&mut self) -> Option<&mut Float> {
+        if self.ty == ObjectKind::Float {
+            Some(unsafe { &mut self.data.float })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained
+    /// [`String`](crate::String), if the object is one.
+    #[inline]
+    pub fn as_string_mut(&mut self) -> Option<&mut crate::String> {
+        if self.ty == ObjectKind::String {
+            Some(unsafe { &mut *self.data.string })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained [`Array`], if the object
+    /// is one.
+    #[inline]
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        if self.ty == ObjectKind::Array {
+            Some(unsafe { &mut *self.data.array })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained [`Dictionary`], if the
+    /// object is one.
+    #[inline]
+    pub fn as_dict_mut(&mut self) -> Option<&mut Dictionary> {
+        if self.ty == ObjectKind::Dictionary {
+            Some(unsafe { &mut *self.data.dictionary })
+        } else {
+            None
+        }
+    }
+
+    /// Extracts the contained [`String`](crate::String), or gives the object
+    /// back if it isn't one.
+    pub fn into_string(self) -> Result<crate::String, Self> {
+        match self.ty {
+            ObjectKind::String => Ok(unsafe { self.into_string_unchecked() }),
+            _ => Err(self),
+        }
+    }
+
+    /// Extracts the contained [`Array`], or gives the object back if it isn't
+    /// one.
+    pub fn into_array(self) -> Result<Array, Self> {
+        match self.ty {
+            ObjectKind::Array => Ok(unsafe { self.into_array_unchecked() }),
+            _ => Err(self),
+        }
+    }
+
+    /// Extracts the contained [`Dictionary`], or gives the object back if it
+    /// isn't one.
+    pub fn into_dict(self) -> Result<Dictionary, Self> {
+        match self.ty {
+            ObjectKind::Dictionary => {
+                Ok(unsafe { self.into_dict_unchecked() })
+            },
+            _ => Err(self),
+        }
+    }
+
+    /// Extracts the contained reference to a Lua function, or gives the
+    /// object back if it isn't one.
+    pub fn into_luaref(self) -> Result<RegistryRef, Self> {
+        match self.ty {
+            ObjectKind::LuaRef => Ok(unsafe { self.into_luaref_unchecked() }),
+            _ => Err(self),
+        }
+    }
+
+    #[inline]
+    fn is_integer(&self) -> bool {
+        matches!(
+            self.ty,
+            ObjectKind::Integer
+                | ObjectKind::Buffer
+                | ObjectKind::Window
+                | ObjectKind::TabPage
+        )
+    }
+}
+
+impl ObjectRef<'_> {
+    /// Returns the kind of the viewed object.
+    pub fn kind(&self) -> ObjectKind {
+        match self {
+            Self::Nil => ObjectKind::Nil,
+            Self::Bool(_) => ObjectKind::Boolean,
+            Self::Int(_) => ObjectKind::Integer,
+            Self::Float(_) => ObjectKind::Float,
+            Self::Str(_) => ObjectKind::String,
+            Self::Array(_) => ObjectKind::Array,
+            Self::Dict(_) => ObjectKind::Dictionary,
+            Self::LuaRef(_) => ObjectKind::LuaRef,
+            Self::Buffer(_) => ObjectKind::Buffer,
+            Self::Window(_) => ObjectKind::Window,
+            Self::TabPage(_) => ObjectKind::TabPage,
+        }
+    }
+}
+
+impl<'a> From<&'a Object> for ObjectRef<'a> {
+    #[inline]
+    fn from(obj: &'a Object) -> Self {
+        obj.view()
+    }
+}
+
+impl<'a> From<&'a Array> for ObjectRef<'a> {
+    #[inline]
+    fn from(array: &'a Array) -> Self {
+        Self::Array(array)
+    }
+}
+
+impl<'a> From<&'a Dictionary> for ObjectRef<'a> {
+    #[inline]
+    fn from(dict: &'a Dictionary) -> Self {
+        Self::Dict(dict)
+    }
+}
+
 macro_rules! clone_copy {
     ($self:expr, $field:ident) => {{
         Self {
@@ -221,7 +511,9 @@ impl Clone for Object {
             ObjectKind::Dictionary => {
                 clone_drop!(self, dictionary, Dictionary)
             },
-            ObjectKind::LuaRef => clone_copy!(self, luaref),
+            ObjectKind::LuaRef => {
+                Self::from_luaref(clone_luaref(unsafe { self.data.luaref }))
+            },
         }
     }
 }
@@ -238,6 +530,8 @@ impl Drop for Object {
                 ManuallyDrop::drop(&mut self.data.dictionary)
             },
 
+            LuaRef => drop(unsafe { RegistryRef::from_raw(self.data.luaref) }),
+
             _ => {},
         }
     }
@@ -264,12 +558,28 @@ impl PartialEq<Self> for Object {
                 String => lhs.string == rhs.string,
                 Array => lhs.array == rhs.array,
                 Dictionary => lhs.dictionary == rhs.dictionary,
-                LuaRef => lhs.luaref == rhs.luaref,
+                LuaRef => {
+                    borrow_luaref(lhs.luaref) == borrow_luaref(rhs.luaref)
+                },
             }
         }
     }
 }
 
+/// Returns a non-owning `RegistryRef` from a raw reference owned by someone
+/// else.
+#[inline]
+fn borrow_luaref(luaref: LuaRef) -> ManuallyDrop<RegistryRef> {
+    ManuallyDrop::new(unsafe { RegistryRef::from_raw(luaref) })
+}
+
+/// Creates a new reference to the value pointed to by a raw reference owned
+/// by someone else.
+#[inline]
+pub(crate) fn clone_luaref(luaref: LuaRef) -> RegistryRef {
+    (*borrow_luaref(luaref)).clone()
+}
+
 impl From<()> for Object {
     fn from(_: ()) -> Self {
         Self::nil()
@@ -425,7 +735,10 @@ where
 impl Pushable for Object {
     unsafe fn push(self, lstate: *mut lua_State) -> Result<c_int, lua::Error> {
         match self.kind() {
-            ObjectKind::Nil => ().push(lstate),
+            ObjectKind::Nil => {
+                lua::ffi::lua_pushnil(lstate);
+                Ok(1)
+            },
             ObjectKind::Boolean => self.as_boolean_unchecked().push(lstate),
             ObjectKind::Integer
             | ObjectKind::Buffer
@@ -435,10 +748,7 @@ impl Pushable for Object {
             ObjectKind::String => self.into_string_unchecked().push(lstate),
             ObjectKind::Array => self.into_array_unchecked().push(lstate),
             ObjectKind::Dictionary => self.into_dict_unchecked().push(lstate),
-            ObjectKind::LuaRef => {
-                Function::<(), ()>::from_ref(self.as_luaref_unchecked())
-                    .push(lstate)
-            },
+            ObjectKind::LuaRef => self.into_luaref_unchecked().push(lstate),
         }
     }
 }
@@ -466,9 +776,9 @@ impl Poppable for Object {
 
             LUA_TTABLE => {
                 if lua::utils::is_table_array(lstate, -1) {
-                    Array::pop(lstate).map(Into::into)
+                    <Array as Poppable>::pop(lstate).map(Into::into)
                 } else {
-                    Dictionary::pop(lstate).map(Into::into)
+                    <Dictionary as Poppable>::pop(lstate).map(Into::into)
                 }
             },
 
@@ -476,6 +786,12 @@ impl Poppable for Object {
 
             LUA_TNONE => Err(lua::Error::PopEmptyStack),
 
+            // `vim.NIL`, e.g. the `null`s decoded by `vim.json.decode`.
+            LUA_TLIGHTUSERDATA if lua_touserdata(lstate, -1).is_null() => {
+                lua_pop(lstate, 1);
+                Ok(Object::nil())
+            },
+
             LUA_TLIGHTUSERDATA | LUA_TUSERDATA | LUA_TTHREAD => {
                 let typename = lua::utils::debug_type(lstate, -1);
                 lua_pop(lstate, 1);
@@ -550,7 +866,7 @@ mod serde {
                 where
                     E: de::Error,
                 {
-                    Ok(Object::from_luaref(f as LuaRef))
+                    Ok(Object::from_luaref(super::clone_luaref(f as LuaRef)))
                 }
 
                 fn visit_seq<A>(
@@ -677,8 +993,35 @@ mod tests {
 
     #[test]
     fn print_luaref() {
-        let obj = Object::from_luaref(42);
+        let obj = Object::from_luaref(unsafe { RegistryRef::from_raw(42) });
         assert_eq!("LuaRef(42)", &format!("{obj:?}"));
         assert_eq!("LuaRef(42)", &format!("{obj}"));
+        // Dropping the object would need a Lua state to release the ref.
+        std::mem::forget(obj);
+    }
+
+    #[test]
+    fn view_and_accessors() {
+        let mut obj = Object::from(Array::from((1, "foo")));
+
+        match obj.view() {
+            ObjectRef::Array(array) => {
+                assert_eq!(Some(1), array[0].as_integer());
+                assert_eq!(ObjectRef::Str(&"foo".into()), array[1].view());
+            },
+            other => panic!("expected an array, got {other:?}"),
+        }
+
+        assert_eq!(None, obj.as_string());
+        obj.as_array_mut().unwrap()[0] = Object::from(2.5);
+        assert_eq!(Some(2.5), obj.as_array().unwrap()[0].as_float());
+
+        let obj = obj.into_string().unwrap_err();
+        assert_eq!(2, obj.into_array().unwrap().len());
+
+        let mut obj = Object::from(41);
+        *obj.as_integer_mut().unwrap() += 1;
+        assert_eq!(ObjectRef::Int(42), obj.view());
+        assert_eq!(None, obj.as_boolean());
     }
 }
@@ -0,0 +1,486 @@
+use luajit_bindings::PathSegment;
+use serde::de::{self, value::BorrowedStrDeserializer};
+
+use super::Result;
+use crate::{Object, ObjectRef};
+
+/// Like [`Deserializer`](super::Deserializer), but deserializes Rust values
+/// from a borrowed [`Object`] instead of an owned one.
+///
+/// Strings are handed out as borrowed data, so types with `&str`, `&[u8]` or
+/// `#[serde(borrow)] Cow<str>` fields can be deserialized without allocating.
+///
+/// ```ignore
+/// use nvim_oxi::serde::BorrowedDeserializer;
+/// use nvim_oxi::{Dictionary, Object};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Opts<'a> {
+///     group: &'a str,
+/// }
+///
+/// let obj = Object::from(Dictionary::from_iter([("group", "foo")]));
+/// let opts = Opts::deserialize(BorrowedDeserializer::new(&obj))?;
+/// assert_eq!("foo", opts.group);
+/// ```
+#[derive(Copy, Clone)]
+pub struct BorrowedDeserializer<'de> {
+    obj: ObjectRef<'de>,
+}
+
+impl<'de> BorrowedDeserializer<'de> {
+    pub fn new(obj: impl Into<ObjectRef<'de>>) -> Self {
+        Self { obj: obj.into() }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for BorrowedDeserializer<'de> {
+    type Error = super::Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf unit unit_struct identifier ignored_any
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            ObjectRef::Nil => visitor.visit_unit(),
+
+            ObjectRef::Bool(b) => visitor.visit_bool(b),
+
+            ObjectRef::Int(n)
+            | ObjectRef::Buffer(n)
+            | ObjectRef::Window(n)
+            | ObjectRef::TabPage(n) => visitor.visit_i64(n),
+
+            ObjectRef::Float(n) => visitor.visit_f64(n),
+
+            ObjectRef::Str(string) => match string.as_str() {
+                Ok(str) => visitor.visit_borrowed_str(str),
+                _ => visitor.visit_borrowed_bytes(string.as_bytes()),
+            },
+
+            ObjectRef::Array(_) => self.deserialize_seq(visitor),
+
+            ObjectRef::Dict(_) => self.deserialize_map(visitor),
+
+            // Same hack used by the owned `Deserializer`.
+            ObjectRef::LuaRef(luaref) => visitor.visit_f32(luaref as f32),
+        }
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            ObjectRef::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let (variant, obj) = match self.obj {
+            ObjectRef::Dict(dict) => {
+                let (variant, value) = match dict.len() {
+                    1 => dict.iter().next().expect("checked length"),
+                    _ => {
+                        return Err(de::Error::invalid_value(
+                            de::Unexpected::Map,
+                            &"dictionary with a single key-value pair",
+                        ))
+                    },
+                };
+
+                (variant, Some(value))
+            },
+
+            ObjectRef::Str(variant) => (variant, None),
+
+            _ => return Err(de::Error::custom("bad enum value")),
+        };
+
+        let variant =
+            variant.as_str().map_err(<super::Error as de::Error>::custom)?;
+
+        visitor.visit_enum(EnumDeserializer { variant, obj })
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            ObjectRef::Array(array) => {
+                let iter = array.as_slice().iter();
+                let mut deserializer = SeqDeserializer { iter, idx: 0 };
+                visitor.visit_seq(&mut deserializer)
+            },
+
+            obj => Err(de::Error::invalid_type(
+                de::Unexpected::Other(&format!("{:?}", obj.kind())),
+                &"array",
+            )),
+        }
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            ObjectRef::Dict(dict) => {
+                let iter = dict.iter();
+                let mut deserializer =
+                    MapDeserializer { iter, key: None, obj: None };
+                visitor.visit_map(&mut deserializer)
+            },
+
+            obj => Err(de::Error::invalid_type(
+                de::Unexpected::Other(&format!("{:?}", obj.kind())),
+                &"dictionary",
+            )),
+        }
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+}
+
+struct SeqDeserializer<'de> {
+    iter: std::slice::Iter<'de, Object>,
+
+    /// The index of the last element that was deserialized.
+    idx: i64,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = super::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if let Some(obj) = self.iter.next() {
+            // Lua arrays are 1-indexed.
+            self.idx += 1;
+            let idx = self.idx;
+            return seed
+                .deserialize(BorrowedDeserializer::new(obj))
+                .map(Some)
+                .map_err(|err| err.in_segment(PathSegment::Index(idx)));
+        }
+
+        Ok(None)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'de> {
+    iter: crate::DictIter<'de>,
+
+    /// The key of the value returned by the last call to `next_key_seed`,
+    /// used to report the path of any error.
+    key: Option<&'de crate::String>,
+
+    obj: Option<&'de Object>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer<'de> {
+    type Error = super::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if let Some((name, obj)) = self.iter.next() {
+            self.key = Some(name);
+            self.obj = Some(obj);
+            return seed
+                .deserialize(BorrowedDeserializer::new(ObjectRef::Str(name)))
+                .map(Some);
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.obj.take() {
+            Some(obj) => seed
+                .deserialize(BorrowedDeserializer::new(obj))
+                .map_err(|err| match self.key.take() {
+                    Some(key) => err.in_segment(PathSegment::Field(
+                        key.to_string_lossy().into_owned(),
+                    )),
+                    None => err,
+                }),
+            _ => Err(de::Error::custom("object is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: &'de str,
+    obj: Option<&'de Object>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = super::Error;
+    type Variant = VariantDeserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let deserializer =
+            VariantDeserializer { variant: self.variant, obj: self.obj };
+        let variant = BorrowedStrDeserializer::new(self.variant);
+        seed.deserialize(variant).map(|v| (v, deserializer))
+    }
+}
+
+struct VariantDeserializer<'de> {
+    variant: &'de str,
+    obj: Option<&'de Object>,
+}
+
+impl VariantDeserializer<'_> {
+    /// Adds the variant's tag to the path of an error raised while
+    /// deserializing its fields.
+    fn in_variant(&self, err: super::Error) -> super::Error {
+        err.in_segment(PathSegment::Field(self.variant.to_owned()))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = super::Error;
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.obj {
+            Some(obj) => seed
+                .deserialize(BorrowedDeserializer::new(obj))
+                .map_err(|err| self.in_variant(err)),
+
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            Some(obj) => de::Deserializer::deserialize_map(
+                BorrowedDeserializer::new(obj),
+                visitor,
+            )
+            .map_err(|err| self.in_variant(err)),
+
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.obj {
+            Some(obj) => de::Deserializer::deserialize_seq(
+                BorrowedDeserializer::new(obj),
+                visitor,
+            )
+            .map_err(|err| self.in_variant(err)),
+
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn unit_variant(self) -> Result<()> {
+        match self.obj {
+            None => Ok(()),
+
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::NewtypeVariant,
+                &"unit variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{Array, Dictionary};
+
+    #[test]
+    fn borrow_str() {
+        #[derive(Deserialize)]
+        struct Opts<'a> {
+            #[serde(borrow)]
+            group: &'a str,
+
+            #[serde(borrow)]
+            pattern: Cow<'a, str>,
+
+            bytes: &'a [u8],
+        }
+
+        let obj = Object::from(Dictionary::from_iter([
+            ("group", Object::from("foo")),
+            ("pattern", Object::from("*.rs")),
+            ("bytes", Object::from(crate::String::from_bytes(vec![0xff]))),
+        ]));
+
+        let opts = Opts::deserialize(BorrowedDeserializer::new(&obj)).unwrap();
+
+        let dict = obj.as_dict().unwrap();
+        let group = dict.get("group").unwrap().as_string().unwrap();
+
+        assert_eq!("foo", opts.group);
+        assert_eq!(group.as_bytes().as_ptr(), opts.group.as_ptr());
+        assert!(matches!(opts.pattern, Cow::Borrowed("*.rs")));
+        assert_eq!(&[0xff], opts.bytes);
+    }
+
+    #[test]
+    fn same_as_owned() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Action<'a> {
+            Close,
+            Open(&'a str),
+            Resize { width: u32, height: Option<u32> },
+        }
+
+        let obj = Object::from(Array::from_iter([
+            Object::from("Close"),
+            Dictionary::from_iter([("Open", "foo")]).into(),
+            Dictionary::from_iter([(
+                "Resize",
+                Dictionary::from_iter([("width", 80)]),
+            )])
+            .into(),
+        ]));
+
+        assert_eq!(
+            vec![
+                Action::Close,
+                Action::Open("foo"),
+                Action::Resize { width: 80, height: None },
+            ],
+            Vec::<Action>::deserialize(BorrowedDeserializer::new(&obj))
+                .unwrap()
+        );
+
+        assert_eq!(
+            Object::deserialize(super::super::Deserializer::new(obj.clone())),
+            Object::deserialize(BorrowedDeserializer::new(&obj))
+        );
+    }
+
+    #[test]
+    fn error_path() {
+        let keymaps = Array::from_iter([
+            Object::from(Dictionary::from_iter([("silent", true)])),
+            Object::from(Dictionary::from_iter([("silent", "yes")])),
+        ]);
+        let opts = Dictionary::from_iter([("keymaps", keymaps)]);
+
+        let err = <HashMap<&str, Vec<HashMap<&str, bool>>>>::deserialize(
+            BorrowedDeserializer::new(&opts),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().ends_with(" at `keymaps[2].silent`"), "{err}");
+    }
+}
@@ -1,5 +1,6 @@
 use std::string::String as StdString;
 
+use luajit_bindings::PathSegment;
 use serde::de::{self, IntoDeserializer};
 
 use super::Result;
@@ -126,7 +127,7 @@ impl<'de> de::Deserializer<'de> for Deserializer {
             ObjectKind::Array => {
                 let iter =
                     unsafe { self.obj.into_array_unchecked() }.into_iter();
-                let mut deserializer = SeqDeserializer { iter };
+                let mut deserializer = SeqDeserializer { iter, idx: 0 };
                 visitor.visit_seq(&mut deserializer)
             },
 
@@ -167,7 +168,8 @@ impl<'de> de::Deserializer<'de> for Deserializer {
             ObjectKind::Dictionary => {
                 let iter =
                     unsafe { self.obj.into_dict_unchecked() }.into_iter();
-                let mut deserializer = MapDeserializer { iter, obj: None };
+                let mut deserializer =
+                    MapDeserializer { iter, key: None, obj: None };
                 visitor.visit_map(&mut deserializer)
             },
 
@@ -206,6 +208,9 @@ impl<'de> de::Deserializer<'de> for Deserializer {
 
 struct SeqDeserializer {
     iter: crate::ArrayIterator,
+
+    /// The index of the last element that was deserialized.
+    idx: i64,
 }
 
 impl<'de> de::SeqAccess<'de> for SeqDeserializer {
@@ -216,7 +221,13 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
         T: de::DeserializeSeed<'de>,
     {
         if let Some(obj) = self.iter.next() {
-            return seed.deserialize(Deserializer { obj }).map(Some);
+            // Lua arrays are 1-indexed.
+            self.idx += 1;
+            let idx = self.idx;
+            return seed
+                .deserialize(Deserializer { obj })
+                .map(Some)
+                .map_err(|err| err.in_segment(PathSegment::Index(idx)));
         }
 
         Ok(None)
@@ -229,6 +240,11 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
 
 struct MapDeserializer {
     iter: crate::DictIterator,
+
+    /// The key of the value returned by the last call to `next_key_seed`,
+    /// used to report the path of any error.
+    key: Option<crate::String>,
+
     obj: Option<Object>,
 }
 
@@ -241,9 +257,14 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
     {
         if let Some((name, obj)) = self.iter.next() {
             self.obj = Some(obj);
-            return seed
-                .deserialize(Deserializer { obj: name.into() })
-                .map(Some);
+            // Deserialize the key by reference so that it can be kept around
+            // without cloning it.
+            let key = self.key.insert(name);
+            return match key.as_str() {
+                Ok(str) => seed.deserialize(str.into_deserializer()),
+                Err(_) => seed.deserialize(key.as_bytes().into_deserializer()),
+            }
+            .map(Some);
         }
 
         Ok(None)
@@ -254,7 +275,14 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
         V: de::DeserializeSeed<'de>,
     {
         match self.obj.take() {
-            Some(obj) => seed.deserialize(Deserializer { obj }),
+            Some(obj) => seed.deserialize(Deserializer { obj }).map_err(
+                |err| match self.key.take() {
+                    Some(key) => err.in_segment(PathSegment::Field(
+                        key.to_string_lossy().into_owned(),
+                    )),
+                    None => err,
+                },
+            ),
             _ => Err(de::Error::custom("object is missing")),
         }
     }
@@ -277,25 +305,39 @@ impl<'de> de::EnumAccess<'de> for EnumDeserializer {
     where
         V: de::DeserializeSeed<'de>,
     {
+        let deserializer = VariantDeserializer {
+            variant: self.variant.clone(),
+            obj: self.obj,
+        };
         let variant = self.variant.into_deserializer();
-        let deserializer = VariantDeserializer { obj: self.obj };
         seed.deserialize(variant).map(|v| (v, deserializer))
     }
 }
 
 struct VariantDeserializer {
+    variant: StdString,
     obj: Option<Object>,
 }
 
+impl VariantDeserializer {
+    /// Adds the variant's tag to the path of an error raised while
+    /// deserializing its fields.
+    fn in_variant(&self, err: super::Error) -> super::Error {
+        err.in_segment(PathSegment::Field(self.variant.clone()))
+    }
+}
+
 impl<'de> de::VariantAccess<'de> for VariantDeserializer {
     type Error = super::Error;
 
-    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value>
     where
         T: de::DeserializeSeed<'de>,
     {
-        match self.obj {
-            Some(obj) => seed.deserialize(Deserializer { obj }),
+        match self.obj.take() {
+            Some(obj) => seed
+                .deserialize(Deserializer { obj })
+                .map_err(|err| self.in_variant(err)),
 
             _ => Err(de::Error::invalid_type(
                 de::Unexpected::UnitVariant,
@@ -305,18 +347,19 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
     }
 
     fn struct_variant<V>(
-        self,
+        mut self,
         _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.obj {
+        match self.obj.take() {
             Some(obj) => de::Deserializer::deserialize_map(
                 Deserializer { obj },
                 visitor,
-            ),
+            )
+            .map_err(|err| self.in_variant(err)),
 
             _ => Err(de::Error::invalid_type(
                 de::Unexpected::UnitVariant,
@@ -325,15 +368,16 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
         }
     }
 
-    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.obj {
+        match self.obj.take() {
             Some(obj) => de::Deserializer::deserialize_seq(
                 Deserializer { obj },
                 visitor,
-            ),
+            )
+            .map_err(|err| self.in_variant(err)),
 
             _ => Err(de::Error::invalid_type(
                 de::Unexpected::UnitVariant,
@@ -404,4 +448,22 @@ mod tests {
         ]);
         assert_eq!(Ok(Object::from(map.clone())), d(map));
     }
+
+    #[test]
+    fn deserialize_error_path() {
+        use std::collections::HashMap;
+
+        let keymaps = Array::from_iter([
+            Object::from(Dictionary::from_iter([("silent", true)])),
+            Object::from(Dictionary::from_iter([("silent", "yes")])),
+        ]);
+        let opts = Dictionary::from_iter([("keymaps", keymaps)]);
+
+        let err = <HashMap<String, Vec<HashMap<String, bool>>>>::deserialize(
+            Deserializer::new(opts.into()),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().ends_with(" at `keymaps[2].silent`"), "{err}");
+    }
 }
@@ -1,5 +1,6 @@
 use std::fmt;
 
+use luajit_bindings::{Path, PathSegment};
 use serde::{de, ser};
 use thiserror::Error as ThisError;
 
@@ -10,8 +11,11 @@ pub enum Error {
     #[error("{0}")]
     Serialize(String),
 
-    #[error("{0}")]
-    Deserialize(String),
+    #[error(
+        "{msg}{}",
+        if path.is_empty() { String::new() } else { format!(" at `{path}`") }
+    )]
+    Deserialize { msg: String, path: Path },
 
     #[error(transparent)]
     FromInt(#[from] std::num::TryFromIntError),
@@ -20,6 +24,17 @@ pub enum Error {
     FromUtf8(#[from] std::string::FromUtf8Error),
 }
 
+impl Error {
+    /// Adds a segment at the start of the path of a deserialization error.
+    /// Any other error is returned unchanged.
+    pub(crate) fn in_segment(mut self, segment: PathSegment) -> Self {
+        if let Self::Deserialize { path, .. } = &mut self {
+            path.prepend(segment);
+        }
+        self
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Self::Serialize(msg.to_string())
@@ -28,6 +43,6 @@ impl ser::Error for Error {
 
 impl de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        Self::Deserialize(msg.to_string())
+        Self::Deserialize { msg: msg.to_string(), path: Path::default() }
     }
 }
@@ -1,9 +1,12 @@
-use std::borrow::Cow;
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::ffi::{c_char, c_int, OsStr};
-use std::mem::ManuallyDrop;
+use std::hash::{Hash, Hasher};
+use std::iter::{Copied, FusedIterator};
+use std::ops::Deref;
 use std::path::PathBuf;
 use std::string::{self, String as StdString};
-use std::{fmt, slice, str};
+use std::{fmt, ptr, slice, str};
 
 use lua::{ffi::*, Poppable, Pushable};
 use luajit_bindings as lua;
@@ -24,7 +27,9 @@ use crate::NonOwning;
 // https://github.com/neovim/neovim/blob/master/src/nvim/api/private/defs.h#L77
 //
 /// Binding to the string type used by Neovim.
-#[derive(Eq, Ord, PartialOrd, Hash)]
+///
+/// The bytes are allocated with `malloc` and `realloc`, just like Neovim
+/// does, so a `String` can be freed by Neovim with `xfree` and vice versa.
 #[repr(C)]
 pub struct String {
     pub(crate) data: *mut c_char,
@@ -38,16 +43,100 @@ impl String {
         Self { data: std::ptr::null_mut(), size: 0 }
     }
 
+    /// Creates a new empty string with space for at least `capacity` bytes.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut string = Self::new();
+        string.realloc(capacity);
+        unsafe { *string.data = 0 };
+        string
+    }
+
     /// Creates a [`String`] from a byte vector.
     #[inline]
-    pub fn from_bytes(mut vec: Vec<u8>) -> Self {
-        vec.reserve_exact(1);
-        Vec::push(&mut vec, 0);
+    pub fn from_bytes(vec: Vec<u8>) -> Self {
+        Self::copied(&vec)
+    }
 
-        let size = vec.len() - 1;
-        let data = vec.leak().as_mut_ptr() as *mut c_char;
+    /// Creates a [`String`] by copying the given bytes.
+    #[inline]
+    fn copied(bytes: &[u8]) -> Self {
+        let mut string = Self::new();
+        string.push_bytes(bytes);
+        string
+    }
+
+    /// Returns the number of bytes the string can hold without reallocating,
+    /// *not* including the final null byte.
+    ///
+    /// Neovim strings don't store their capacity, so it's asked to the
+    /// allocator. On platforms where that's not possible this is the same as
+    /// [`len`](String::len).
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        if self.data.is_null() {
+            0
+        } else {
+            // The allocator can give us more than we asked for, but never
+            // less, so there's always room for the null byte.
+            allocated_size(self.data as *mut _, self.size + 1) - 1
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more bytes. The buffer
+    /// grows geometrically, so pushing to a string takes amortized constant
+    /// time.
+    pub fn reserve(&mut self, additional: usize) {
+        let len =
+            self.size.checked_add(additional).expect("capacity overflow");
 
-        Self { data, size }
+        let capacity = self.capacity();
+
+        if len > capacity {
+            self.realloc(len.max(capacity.saturating_mul(2)));
+            unsafe { *self.data.add(self.size) = 0 };
+        }
+    }
+
+    /// Appends the given bytes to the end of the string.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        self.reserve(bytes.len());
+
+        let size = self.size + bytes.len();
+
+        unsafe {
+            let end = self.data.add(self.size) as *mut u8;
+            ptr::copy_nonoverlapping(bytes.as_ptr(), end, bytes.len());
+            *self.data.add(size) = 0;
+        }
+
+        self.size = size;
+    }
+
+    /// Appends the given string slice to the end of the string.
+    #[inline]
+    pub fn push_str(&mut self, str: &str) {
+        self.push_bytes(str.as_bytes())
+    }
+
+    /// Shortens the string to `len` bytes. Does nothing if `len` is greater
+    /// than the string's current length.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.size {
+            self.size = len;
+            unsafe { *self.data.add(len) = 0 };
+        }
+    }
+
+    /// Removes all the bytes from the string, keeping its buffer.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0)
     }
 
     /// Returns `true` if the `String` has a length of zero, and `false`
@@ -80,6 +169,21 @@ impl String {
         }
     }
 
+    /// Returns an iterator over the bytes of the string.
+    #[inline]
+    pub fn bytes(&self) -> Copied<slice::Iter<'_, u8>> {
+        self.as_bytes().iter().copied()
+    }
+
+    /// Returns an iterator over the characters of the string. Invalid UTF-8
+    /// sequences are yielded as `�`, like [`to_string_lossy`] does.
+    ///
+    /// [`to_string_lossy`]: String::to_string_lossy
+    #[inline]
+    pub fn chars(&self) -> StringChars<'_> {
+        StringChars { bytes: self.as_bytes() }
+    }
+
     /// Returns a string slice of this `String`'s contents. Fails if it doesn't
     /// contain a valid UTF-8 byte sequence.
     #[inline]
@@ -98,18 +202,9 @@ impl String {
     /// Converts the `String` into a byte vector, consuming it.
     #[inline]
     pub fn into_bytes(self) -> Vec<u8> {
-        if self.data.is_null() {
-            Vec::new()
-        } else {
-            unsafe {
-                let mdrop = ManuallyDrop::new(self);
-                Vec::from_raw_parts(
-                    mdrop.data.cast::<u8>(),
-                    mdrop.size,
-                    mdrop.size,
-                )
-            }
-        }
+        // The bytes are allocated with `malloc`, so they have to be copied
+        // into a buffer owned by Rust's allocator.
+        self.as_bytes().to_owned()
     }
 
     /// Converts the `String` into Rust's `std::string::String`, consuming it.
@@ -125,8 +220,98 @@ impl String {
     pub fn non_owning(&self) -> NonOwning<'_, String> {
         NonOwning::new(Self { ..*self })
     }
+
+    /// Reallocates the buffer to hold `len` bytes plus the null terminator.
+    fn realloc(&mut self, len: usize) {
+        let size = len.checked_add(1).expect("capacity overflow");
+
+        let ptr =
+            unsafe { libc::realloc(self.data as *mut libc::c_void, size) };
+
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(
+                std::alloc::Layout::array::<u8>(size).unwrap(),
+            );
+        }
+
+        self.data = ptr as *mut c_char;
+    }
+}
+
+/// Returns the size of the block allocated by `malloc` at `ptr`, or `len` if
+/// the platform's allocator can't tell.
+#[allow(unused_variables)]
+fn allocated_size(ptr: *mut libc::c_void, len: usize) -> usize {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd"
+    ))]
+    return unsafe { libc::malloc_usable_size(ptr as _) };
+
+    #[cfg(target_vendor = "apple")]
+    return unsafe { libc::malloc_size(ptr as _) };
+
+    #[cfg(windows)]
+    return unsafe { libc::_msize(ptr) };
+
+    #[allow(unreachable_code)]
+    len
 }
 
+/// An iterator over the [`char`]s of a [`String`], created by
+/// [`String::chars`].
+#[derive(Clone)]
+pub struct StringChars<'a> {
+    bytes: &'a [u8],
+}
+
+impl Iterator for StringChars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        // A valid character is at most 4 bytes long.
+        let chunk = &self.bytes[..self.bytes.len().min(4)];
+
+        let (ch, len) = match str::from_utf8(chunk) {
+            Ok(str) => {
+                let ch = str.chars().next().expect("chunk isn't empty");
+                (ch, ch.len_utf8())
+            },
+
+            Err(err) if err.valid_up_to() > 0 => {
+                let valid = &chunk[..err.valid_up_to()];
+                let ch = unsafe { str::from_utf8_unchecked(valid) }
+                    .chars()
+                    .next()
+                    .expect("chunk isn't empty");
+                (ch, ch.len_utf8())
+            },
+
+            // If the sequence is only cut short by the end of the string it
+            // becomes a single replacement character.
+            Err(err) => (
+                char::REPLACEMENT_CHARACTER,
+                err.error_len().unwrap_or(chunk.len()),
+            ),
+        };
+
+        self.bytes = &self.bytes[len..];
+        Some(ch)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.bytes.len().saturating_add(3) / 4, Some(self.bytes.len()))
+    }
+}
+
+impl FusedIterator for StringChars<'_> {}
+
 impl Default for String {
     #[inline]
     fn default() -> Self {
@@ -146,32 +331,123 @@ impl fmt::Display for String {
     }
 }
 
+impl fmt::Write for String {
+    #[inline]
+    fn write_str(&mut self, str: &str) -> fmt::Result {
+        self.push_str(str);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_char(&mut self, ch: char) -> fmt::Result {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+}
+
 impl Clone for String {
     fn clone(&self) -> Self {
-        Self::from_bytes(self.as_bytes().to_owned())
+        Self::copied(self.as_bytes())
     }
 }
 
 impl Drop for String {
     fn drop(&mut self) {
-        // One extra for null terminator.
-        let _ = unsafe {
-            Vec::from_raw_parts(self.data, self.size + 1, self.size + 1)
-        };
+        if !self.data.is_null() {
+            unsafe { libc::free(self.data as *mut libc::c_void) };
+        }
+    }
+}
+
+impl Deref for String {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for String {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<[u8]> for String {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+// `Hash`, `Eq` and `Ord` are all implemented on the bytes of the string, so
+// that they agree with each other and with the `Borrow<[u8]>` impl.
+impl Hash for String {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl Eq for String {}
+
+impl PartialOrd for String {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for String {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Extend<char> for String {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        iter.for_each(|ch| self.push_str(ch.encode_utf8(&mut [0; 4])))
+    }
+}
+
+impl<'a> Extend<&'a str> for String {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|str| self.push_str(str))
+    }
+}
+
+impl FromIterator<char> for String {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut string = Self::new();
+        string.extend(iter);
+        string
+    }
+}
+
+impl<'a> FromIterator<&'a str> for String {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut string = Self::new();
+        string.extend(iter);
+        string
     }
 }
 
 impl From<StdString> for String {
     #[inline]
     fn from(string: StdString) -> Self {
-        Self::from_bytes(string.into_bytes())
+        Self::copied(string.as_bytes())
     }
 }
 
 impl From<&str> for String {
     #[inline]
     fn from(str: &str) -> Self {
-        Self::from_bytes(str.as_bytes().to_owned())
+        Self::copied(str.as_bytes())
     }
 }
 
@@ -293,7 +569,7 @@ mod serde {
                 where
                     E: de::Error,
                 {
-                    Ok(crate::String::from_bytes(b.to_owned()))
+                    Ok(crate::String::copied(b))
                 }
 
                 fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
@@ -352,4 +628,73 @@ mod tests {
         let bytes = s.into_bytes();
         assert_eq!(&[104, 101, 108, 108, 111][..], &bytes[..]);
     }
+
+    #[test]
+    fn push_and_write() {
+        use std::fmt::Write;
+
+        let mut s = String::with_capacity(16);
+        assert!(s.is_empty());
+        assert!(s.capacity() >= 16);
+
+        let ptr = s.as_ptr();
+        s.push_str("foo");
+        assert_eq!(ptr, s.as_ptr());
+        assert!(s.capacity() >= 16);
+        s.extend([' ']);
+        write!(s, "{}-é", 1).unwrap();
+        assert_eq!(s, "foo 1-é");
+        assert_eq!(0, unsafe { *s.as_ptr().add(s.len()) });
+
+        s.truncate(3);
+        assert_eq!(s, "foo");
+
+        s.clear();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn push_grows_geometrically() {
+        let mut s = String::new();
+        let mut reallocs = 0;
+
+        for _ in 0..10_000 {
+            let capacity = s.capacity();
+            s.push_str("a");
+            if s.capacity() != capacity {
+                reallocs += 1;
+            }
+        }
+
+        assert_eq!(10_000, s.len());
+        assert!(reallocs < 20, "{reallocs} reallocations");
+    }
+
+    #[test]
+    fn hash_and_ord() {
+        use std::collections::{BTreeSet, HashMap};
+
+        let mut map = HashMap::new();
+        map.insert(String::from("foo"), 1);
+        map.insert(String::from("foo"), 2);
+        assert_eq!(1, map.len());
+        assert_eq!(Some(&2), map.get(b"foo".as_slice()));
+
+        let set = BTreeSet::from_iter(["b", "c", "a"].map(String::from));
+        let set = set.into_iter().map(|s| s.into_string().unwrap());
+        assert_eq!(vec!["a", "b", "c"], set.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bytes_and_chars() {
+        let s = String::from_bytes(b"a\xc3\xa9\xff\xe2\x82".to_vec());
+
+        assert_eq!(6, s.bytes().count());
+        assert_eq!(&s[..1], b"a");
+        assert_eq!(
+            vec!['a', 'é', '\u{fffd}', '\u{fffd}'],
+            s.chars().collect::<Vec<_>>()
+        );
+        assert_eq!(s.to_string_lossy(), s.chars().collect::<StdString>());
+    }
 }
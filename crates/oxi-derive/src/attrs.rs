@@ -0,0 +1,262 @@
+//! Parsing of the `#[lua(..)]` and `#[object(..)]` helper attributes.
+
+use syn::ext::IdentExt;
+use syn::{
+    parse_quote,
+    Attribute,
+    Error,
+    Field,
+    Lit,
+    LitStr,
+    Meta,
+    NestedMeta,
+    Path,
+    Variant,
+};
+
+use crate::case::RenameRule;
+
+/// The helper attribute the attributes are read from.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Namespace {
+    /// `#[lua(..)]`, used by `Pushable` and `Poppable`.
+    Lua,
+
+    /// `#[object(..)]`, used by `FromObject` and `ToObject`.
+    Object,
+}
+
+impl Namespace {
+    fn ident(self) -> &'static str {
+        match self {
+            Self::Lua => "lua",
+            Self::Object => "object",
+        }
+    }
+
+    fn default_crate(self) -> Path {
+        match self {
+            Self::Lua => parse_quote!(::nvim_oxi::lua),
+            Self::Object => parse_quote!(::nvim_oxi),
+        }
+    }
+}
+
+/// Attributes that can be placed on the struct or enum being derived.
+pub(crate) struct ContainerAttrs {
+    /// Path to the crate the derived trait is defined in, `::nvim_oxi::lua`
+    /// for the Lua traits and `::nvim_oxi` for the `Object` ones.
+    pub(crate) krate: Path,
+
+    pub(crate) rename_all: Option<RenameRule>,
+}
+
+/// Attributes that can be placed on a struct or variant field.
+pub(crate) struct FieldAttrs {
+    pub(crate) rename: Option<String>,
+
+    pub(crate) default: Option<FieldDefault>,
+
+    /// Only supported by `#[object(..)]`.
+    pub(crate) skip: bool,
+
+    /// Only supported by `#[object(..)]`.
+    pub(crate) flatten: bool,
+}
+
+/// How to fill in a field whose value is `nil`.
+pub(crate) enum FieldDefault {
+    /// Use the field's `Default` implementation.
+    Trait,
+
+    /// Call the function at the given path.
+    Path(Path),
+}
+
+/// Attributes that can be placed on an enum variant.
+pub(crate) struct VariantAttrs {
+    pub(crate) rename: Option<String>,
+}
+
+impl ContainerAttrs {
+    pub(crate) fn parse(
+        attrs: &[Attribute],
+        namespace: Namespace,
+    ) -> syn::Result<Self> {
+        let mut this =
+            Self { krate: namespace.default_crate(), rename_all: None };
+
+        for meta in metas(attrs, namespace)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("crate") => {
+                    this.krate = lit_str(&nv.lit)?.parse()?;
+                },
+
+                Meta::NameValue(nv) if nv.path.is_ident("rename_all") => {
+                    this.rename_all =
+                        Some(RenameRule::from_lit(lit_str(&nv.lit)?)?);
+                },
+
+                _ => return Err(unknown_attribute(&meta)),
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(
+        field: &Field,
+        namespace: Namespace,
+    ) -> syn::Result<Self> {
+        let mut this =
+            Self { rename: None, default: None, skip: false, flatten: false };
+
+        for meta in metas(&field.attrs, namespace)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                    this.rename = Some(lit_str(&nv.lit)?.value());
+                },
+
+                Meta::Path(path) if path.is_ident("default") => {
+                    this.default = Some(FieldDefault::Trait);
+                },
+
+                Meta::NameValue(nv) if nv.path.is_ident("default") => {
+                    let path = lit_str(&nv.lit)?.parse()?;
+                    this.default = Some(FieldDefault::Path(path));
+                },
+
+                Meta::Path(path)
+                    if path.is_ident("skip")
+                        && namespace == Namespace::Object =>
+                {
+                    this.skip = true;
+                },
+
+                Meta::Path(path)
+                    if path.is_ident("flatten")
+                        && namespace == Namespace::Object =>
+                {
+                    if field.ident.is_none() {
+                        return Err(Error::new_spanned(
+                            path,
+                            "only named fields can be flattened",
+                        ));
+                    }
+                    this.flatten = true;
+                },
+
+                _ => return Err(unknown_attribute(&meta)),
+            }
+        }
+
+        if this.skip && this.flatten {
+            return Err(Error::new_spanned(
+                field,
+                "a field can't be both skipped and flattened",
+            ));
+        }
+
+        Ok(this)
+    }
+}
+
+impl VariantAttrs {
+    pub(crate) fn parse(
+        variant: &Variant,
+        namespace: Namespace,
+    ) -> syn::Result<Self> {
+        let mut this = Self { rename: None };
+
+        for meta in metas(&variant.attrs, namespace)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                    this.rename = Some(lit_str(&nv.lit)?.value());
+                },
+
+                _ => return Err(unknown_attribute(&meta)),
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+/// Returns the key used to store a named field in a Lua table.
+pub(crate) fn field_key(
+    field: &Field,
+    attrs: &FieldAttrs,
+    container: &ContainerAttrs,
+) -> String {
+    if let Some(rename) = &attrs.rename {
+        return rename.clone();
+    }
+
+    let name = field.ident.as_ref().expect("field is named").unraw();
+
+    match container.rename_all {
+        Some(rule) => rule.apply_to_field(&name.to_string()),
+        None => name.to_string(),
+    }
+}
+
+/// Returns the string used to tag an enum variant on the Lua side.
+pub(crate) fn variant_tag(
+    variant: &Variant,
+    attrs: &VariantAttrs,
+    container: &ContainerAttrs,
+) -> String {
+    if let Some(rename) = &attrs.rename {
+        return rename.clone();
+    }
+
+    let name = variant.ident.unraw().to_string();
+
+    match container.rename_all {
+        Some(rule) => rule.apply_to_variant(&name),
+        None => name,
+    }
+}
+
+/// Collects the contents of all the `#[lua(..)]` or `#[object(..)]`
+/// attributes.
+fn metas(attrs: &[Attribute], namespace: Namespace) -> syn::Result<Vec<Meta>> {
+    let ident = namespace.ident();
+    let mut metas = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(ident)) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    format!("expected `#[{ident}(..)]`"),
+                ))
+            },
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(meta) => metas.push(meta),
+                NestedMeta::Lit(lit) => {
+                    return Err(Error::new_spanned(lit, "unexpected literal"))
+                },
+            }
+        }
+    }
+
+    Ok(metas)
+}
+
+fn lit_str(lit: &Lit) -> syn::Result<&LitStr> {
+    match lit {
+        Lit::Str(str) => Ok(str),
+        other => Err(Error::new_spanned(other, "expected a string literal")),
+    }
+}
+
+fn unknown_attribute(meta: &Meta) -> Error {
+    Error::new_spanned(meta, "unknown attribute")
+}
@@ -0,0 +1,123 @@
+//! Case conversions used by the `rename_all` container attribute.
+
+use syn::{Error, LitStr};
+
+/// The naming conventions supported by `#[lua(rename_all = "..")]`.
+#[derive(Copy, Clone)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    pub(crate) fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return Err(Error::new_spanned(lit, "unknown rename rule")),
+        })
+    }
+
+    /// Applies the rule to a variant name, which is assumed to be written
+    /// in `PascalCase`.
+    pub(crate) fn apply_to_variant(self, variant: &str) -> String {
+        use RenameRule::*;
+        match self {
+            Pascal => variant.to_owned(),
+            Lower => variant.to_ascii_lowercase(),
+            Upper => variant.to_ascii_uppercase(),
+            Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => {
+                        first.to_ascii_lowercase().to_string() + chars.as_str()
+                    },
+                    None => String::new(),
+                }
+            },
+            Snake => {
+                let mut snake = String::with_capacity(variant.len());
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            },
+            ScreamingSnake => {
+                Snake.apply_to_variant(variant).to_ascii_uppercase()
+            },
+            Kebab => Snake.apply_to_variant(variant).replace('_', "-"),
+            ScreamingKebab => {
+                ScreamingSnake.apply_to_variant(variant).replace('_', "-")
+            },
+        }
+    }
+
+    /// Applies the rule to a field name, which is assumed to be written in
+    /// `snake_case`.
+    pub(crate) fn apply_to_field(self, field: &str) -> String {
+        use RenameRule::*;
+        match self {
+            Lower | Snake => field.to_owned(),
+            Upper | ScreamingSnake => field.to_ascii_uppercase(),
+            Pascal => {
+                let mut pascal = String::with_capacity(field.len());
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            },
+            Camel => {
+                let pascal = Pascal.apply_to_field(field);
+                Camel.apply_to_variant(&pascal)
+            },
+            Kebab => field.replace('_', "-"),
+            ScreamingKebab => {
+                ScreamingSnake.apply_to_field(field).replace('_', "-")
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenameRule::*;
+
+    #[test]
+    fn rename_variants() {
+        assert_eq!("very_tasty", Snake.apply_to_variant("VeryTasty"));
+        assert_eq!("veryTasty", Camel.apply_to_variant("VeryTasty"));
+        assert_eq!("VERY-TASTY", ScreamingKebab.apply_to_variant("VeryTasty"));
+        assert_eq!("verytasty", Lower.apply_to_variant("VeryTasty"));
+    }
+
+    #[test]
+    fn rename_fields() {
+        assert_eq!("VeryTasty", Pascal.apply_to_field("very_tasty"));
+        assert_eq!("veryTasty", Camel.apply_to_field("very_tasty"));
+        assert_eq!("very-tasty", Kebab.apply_to_field("very_tasty"));
+        assert_eq!("VERY_TASTY", Upper.apply_to_field("very_tasty"));
+    }
+}
@@ -0,0 +1,377 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse_quote,
+    Data,
+    DataEnum,
+    DeriveInput,
+    Error,
+    Fields,
+    LitByteStr,
+    Path,
+};
+
+use crate::attrs::{
+    self,
+    ContainerAttrs,
+    FieldAttrs,
+    FieldDefault,
+    Namespace,
+    VariantAttrs,
+};
+use crate::utils::{add_trait_bounds, bindings};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = ContainerAttrs::parse(&input.attrs, Namespace::Object)?;
+    let krate = &container.krate;
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => quote! {
+                <() as #krate::conversion::FromObject>::from_object(obj)?;
+                Ok(Self)
+            },
+
+            fields => {
+                let value = from_fields(
+                    quote!(Self),
+                    fields,
+                    quote!(obj),
+                    &container,
+                )?;
+                quote! { Ok(#value) }
+            },
+        },
+
+        Data::Enum(data) => from_enum(data, &container)?,
+
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "unions can't be converted from Objects",
+            ))
+        },
+    };
+
+    let name = &input.ident;
+    let bound: Path = parse_quote!(#krate::conversion::FromObject);
+    let generics = add_trait_bounds(input.generics.clone(), &bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::conversion::FromObject for #name #ty_generics
+            #where_clause
+        {
+            fn from_object(
+                obj: #krate::Object,
+            ) -> ::std::result::Result<Self, #krate::conversion::Error> {
+                #body
+            }
+        }
+    })
+}
+
+/// Converts an enum. Unit variants are expected to be strings, all the
+/// others dictionaries with a single key-value pair mapping the variant's tag
+/// to its fields.
+fn from_enum(
+    data: &DataEnum,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+
+    let mut unit_arms = Vec::new();
+    let mut dict_arms = Vec::new();
+
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(variant, Namespace::Object)?;
+        let tag = attrs::variant_tag(variant, &attrs, container);
+        let tag = LitByteStr::new(tag.as_bytes(), Span::call_site());
+        let ident = &variant.ident;
+
+        match &variant.fields {
+            Fields::Unit => {
+                unit_arms.push(quote! { #tag => Self::#ident, });
+            },
+
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                let ty = &unnamed.unnamed[0].ty;
+                dict_arms.push(quote! {
+                    #tag => Self::#ident(
+                        <#ty as #krate::conversion::FromObject>::from_object(
+                            __value,
+                        )?
+                    ),
+                });
+            },
+
+            fields => {
+                let value = from_fields(
+                    quote!(Self::#ident),
+                    fields,
+                    quote!(__value),
+                    container,
+                )?;
+                dict_arms.push(quote! { #tag => #value, });
+            },
+        }
+    }
+
+    let unknown_variant = quote! {
+        __other => {
+            return Err(#krate::conversion::Error::UnknownVariant {
+                variant: ::std::string::String::from_utf8_lossy(__other)
+                    .into_owned(),
+            })
+        },
+    };
+
+    let expected = match (unit_arms.is_empty(), dict_arms.is_empty()) {
+        (false, true) => "string",
+        (true, false) => "dictionary",
+        _ => "string or dictionary",
+    };
+
+    let string_arm = (!unit_arms.is_empty()).then(|| {
+        quote! {
+            #krate::ObjectKind::String => {
+                let __tag = <#krate::String as
+                    #krate::conversion::FromObject>::from_object(obj)?;
+
+                Ok(match __tag.as_bytes() {
+                    #(#unit_arms)*
+                    #unknown_variant
+                })
+            },
+        }
+    });
+
+    let dict_arm = (!dict_arms.is_empty()).then(|| {
+        quote! {
+            #krate::ObjectKind::Dictionary => {
+                let __dict = <#krate::Dictionary as
+                    #krate::conversion::FromObject>::from_object(obj)?;
+
+                if __dict.len() != 1 {
+                    return Err(#krate::conversion::Error::FromWrongLength {
+                        expected: 1,
+                        actual: __dict.len(),
+                    });
+                }
+
+                let (__tag, __value) = __dict.into_iter().next().unwrap();
+
+                Ok(match __tag.as_bytes() {
+                    #(#dict_arms)*
+                    #unknown_variant
+                })
+            },
+        }
+    });
+
+    Ok(quote! {
+        match obj.kind() {
+            #string_arm
+            #dict_arm
+            __other => Err(#krate::conversion::Error::FromWrongType {
+                expected: #expected,
+                actual: __other.as_static(),
+            }),
+        }
+    })
+}
+
+/// Returns an expression converting `obj` into a `constructor { .. }` or
+/// `constructor(..)`, from a dictionary or an array respectively.
+fn from_fields(
+    constructor: TokenStream,
+    fields: &Fields,
+    obj: TokenStream,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(_) => from_dict(constructor, fields, obj, container),
+        _ => from_array(constructor, fields, obj, container),
+    }
+}
+
+/// Returns an expression building a struct or variant with named fields
+/// from a dictionary.
+///
+/// The dictionary is consumed while looking for the keys, so the values are
+/// moved into the fields without being cloned.
+fn from_dict(
+    constructor: TokenStream,
+    fields: &Fields,
+    obj: TokenStream,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+
+    let mut declarations = Vec::new();
+    let mut arms = Vec::new();
+    let mut finalizers = Vec::new();
+    let mut is_flattened = false;
+
+    for (field, binding) in fields.iter().zip(&bindings) {
+        let attrs = FieldAttrs::parse(field, Namespace::Object)?;
+        let ty = &field.ty;
+
+        let default = attrs.default.as_ref().map(|default| match default {
+            FieldDefault::Trait => quote!(::std::default::Default::default()),
+            FieldDefault::Path(path) => quote!(#path()),
+        });
+
+        if attrs.skip {
+            let default = default
+                .unwrap_or_else(|| quote!(::std::default::Default::default()));
+            finalizers.push(quote! { let #binding: #ty = #default; });
+            continue;
+        }
+
+        if attrs.flatten {
+            if is_flattened {
+                return Err(Error::new_spanned(
+                    field,
+                    "only one field can be flattened",
+                ));
+            }
+            is_flattened = true;
+            finalizers.push(quote! {
+                let #binding = <#ty as #krate::conversion::FromObject>
+                    ::from_object(__rest.into())?;
+            });
+            continue;
+        }
+
+        let key = attrs::field_key(field, &attrs, container);
+        let key_bytes = LitByteStr::new(key.as_bytes(), Span::call_site());
+
+        declarations.push(quote! {
+            let mut #binding: ::std::option::Option<#ty> = None;
+        });
+
+        arms.push(quote! {
+            #key_bytes => {
+                #binding = Some(
+                    <#ty as #krate::conversion::FromObject>::from_object(
+                        __value,
+                    )?,
+                );
+            },
+        });
+
+        // A missing key is treated like a `nil` value, so that e.g.
+        // `Option`s don't need to be marked as `default`.
+        let missing = default.unwrap_or_else(|| {
+            quote! {
+                <#ty as #krate::conversion::FromObject>::from_object(
+                    #krate::Object::nil(),
+                )
+                .map_err(|_| #krate::conversion::Error::MissingField {
+                    field: #key,
+                })?
+            }
+        });
+
+        finalizers.push(quote! {
+            let #binding: #ty = match #binding {
+                Some(__value) => __value,
+                None => #missing,
+            };
+        });
+    }
+
+    let (rest, other_arm) = match is_flattened {
+        true => (
+            quote! { let mut __rest = #krate::Dictionary::new(); },
+            quote! {
+                _ => __rest.push(#krate::KeyValuePair::from((__key, __value))),
+            },
+        ),
+        false => (quote! {}, quote! { _ => {} }),
+    };
+
+    let idents = fields.iter().map(|field| &field.ident);
+
+    Ok(quote! {{
+        let __dict = <#krate::Dictionary as
+            #krate::conversion::FromObject>::from_object(#obj)?;
+
+        #(#declarations)*
+        #rest
+
+        for (__key, __value) in __dict {
+            if __value.is_nil() {
+                continue;
+            }
+
+            match __key.as_bytes() {
+                #(#arms)*
+                #other_arm
+            }
+        }
+
+        #(#finalizers)*
+
+        #constructor { #(#idents: #bindings),* }
+    }})
+}
+
+/// Returns an expression building a tuple struct or variant from an array.
+fn from_array(
+    constructor: TokenStream,
+    fields: &Fields,
+    obj: TokenStream,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+    let mut len = 0usize;
+
+    let values = fields
+        .iter()
+        .zip(&bindings)
+        .map(|(field, binding)| {
+            let attrs = FieldAttrs::parse(field, Namespace::Object)?;
+            let ty = &field.ty;
+
+            let value = if attrs.skip {
+                match attrs.default {
+                    Some(FieldDefault::Path(path)) => quote!(#path()),
+                    _ => quote!(::std::default::Default::default()),
+                }
+            } else {
+                len += 1;
+                quote! {
+                    <#ty as #krate::conversion::FromObject>::from_object(
+                        __items.next().unwrap(),
+                    )?
+                }
+            };
+
+            Ok(quote! { let #binding: #ty = #value; })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let items =
+        (len > 0).then(|| quote! { let mut __items = __array.into_iter(); });
+
+    Ok(quote! {{
+        let __array = <#krate::Array as
+            #krate::conversion::FromObject>::from_object(#obj)?;
+
+        if __array.len() != #len {
+            return Err(#krate::conversion::Error::FromWrongLength {
+                expected: #len,
+                actual: __array.len(),
+            });
+        }
+
+        #items
+        #(#values)*
+
+        #constructor(#(#bindings),*)
+    }})
+}
@@ -0,0 +1,165 @@
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attrs;
+mod case;
+mod from_object;
+mod poppable;
+mod pushable;
+mod to_object;
+mod utils;
+
+/// Derives the `Pushable` trait, pushing the value directly onto the Lua
+/// stack without going through an `Object`.
+///
+/// - structs with named fields are pushed as tables;
+/// - tuple structs are pushed as array tables;
+/// - unit structs are pushed as `nil`;
+/// - unit enum variants are pushed as strings, all the other variants as
+///   tables with a single key-value pair mapping the variant's name to its
+///   fields.
+///
+/// # Attributes
+///
+/// - `#[lua(rename_all = "..")]` on a struct or enum renames all its fields
+///   or variants according to the given case convention, e.g. `"snake_case"`
+///   or `"camelCase"`;
+/// - `#[lua(rename = "..")]` on a field or variant changes the name it's
+///   pushed with;
+/// - `#[lua(crate = "..")]` on a struct or enum sets the path to the Lua
+///   bindings, `::nvim_oxi::lua` by default.
+///
+/// # Examples
+///
+/// ```ignore
+/// use nvim_oxi::lua::{Poppable, Pushable};
+///
+/// #[derive(Pushable, Poppable)]
+/// struct Car {
+///     manufacturer: CarManufacturer,
+///     miles: u32,
+///     #[lua(default)]
+///     works: bool,
+/// }
+///
+/// #[derive(Pushable, Poppable)]
+/// #[lua(rename_all = "snake_case")]
+/// enum CarManufacturer {
+///     Nikola,
+///     Tesla,
+///     Volkswagen,
+/// }
+/// ```
+#[proc_macro_derive(Pushable, attributes(lua))]
+pub fn derive_pushable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    pushable::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `Poppable` trait, popping the value directly off the Lua stack
+/// without going through an `Object`.
+///
+/// The expected Lua representation is the same one produced by
+/// [`macro@Pushable`].
+///
+/// # Attributes
+///
+/// On top of the ones supported by [`macro@Pushable`], fields also accept:
+///
+/// - `#[lua(default)]`, which uses the field's `Default` implementation if the
+///   value is `nil`;
+/// - `#[lua(default = "path")]`, which calls the function at `path` if the
+///   value is `nil`.
+#[proc_macro_derive(Poppable, attributes(lua))]
+pub fn derive_poppable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    poppable::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `ToObject` trait, converting the value directly into an
+/// `Object` without going through `serde`.
+///
+/// The representation is the same one used by [`macro@Pushable`]:
+///
+/// - structs with named fields are converted into dictionaries, leaving out
+///   the fields whose value is `nil`;
+/// - tuple structs are converted into arrays;
+/// - unit structs are converted into `nil`;
+/// - unit enum variants are converted into strings, all the other variants
+///   into dictionaries with a single key-value pair mapping the variant's
+///   name to its fields.
+///
+/// Fields can be of any type implementing `ToObject`, including `Buffer`s,
+/// `Window`s and `TabPage`s.
+///
+/// # Attributes
+///
+/// - `#[object(rename_all = "..")]` on a struct or enum renames all its
+///   fields or variants according to the given case convention;
+/// - `#[object(rename = "..")]` on a field or variant changes the key it's
+///   stored under;
+/// - `#[object(skip)]` on a field leaves it out;
+/// - `#[object(flatten)]` on a named field whose value is converted into a
+///   dictionary merges the dictionary's pairs with the other fields;
+/// - `#[object(crate = "..")]` on a struct or enum sets the path to the
+///   crate defining the `Object` types, `::nvim_oxi` by default.
+///
+/// # Examples
+///
+/// ```ignore
+/// use nvim_oxi::api::Buffer;
+/// use nvim_oxi::conversion::{FromObject, ToObject};
+///
+/// #[derive(FromObject, ToObject)]
+/// #[object(rename_all = "camelCase")]
+/// struct Preview {
+///     buffer: Buffer,
+///     max_height: Option<u32>,
+///     #[object(default)]
+///     focusable: bool,
+///     #[object(skip)]
+///     is_open: bool,
+/// }
+/// ```
+#[proc_macro_derive(ToObject, attributes(object))]
+pub fn derive_to_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    to_object::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `FromObject` trait, converting an `Object` directly into the
+/// value without going through `serde`.
+///
+/// The expected representation is the same one produced by
+/// [`macro@ToObject`]. Missing keys are treated like `nil` values, so
+/// `Option` fields can be left out without marking them as `default`.
+///
+/// # Attributes
+///
+/// On top of the ones supported by [`macro@ToObject`], fields also accept:
+///
+/// - `#[object(default)]`, which uses the field's `Default` implementation if
+///   the value is `nil` or missing;
+/// - `#[object(default = "path")]`, which calls the function at `path` if
+///   the value is `nil` or missing.
+///
+/// Skipped fields are set to their default value. A flattened field is built
+/// from a dictionary containing all the keys that don't match any of the
+/// other fields, and there can be at most one of them.
+#[proc_macro_derive(FromObject, attributes(object))]
+pub fn derive_from_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    from_object::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,362 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse_quote,
+    Data,
+    DataEnum,
+    DeriveInput,
+    Error,
+    Fields,
+    LitByteStr,
+    LitStr,
+    Path,
+};
+
+use crate::attrs::{
+    self,
+    ContainerAttrs,
+    FieldAttrs,
+    FieldDefault,
+    Namespace,
+    VariantAttrs,
+};
+use crate::utils::{add_trait_bounds, bindings};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = ContainerAttrs::parse(&input.attrs, Namespace::Lua)?;
+    let krate = &container.krate;
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => quote! {
+                <() as #krate::Poppable>::pop(lstate)?;
+                Ok(Self)
+            },
+
+            fields => {
+                let table = pop_table(quote!(Self), fields, &container)?;
+                quote! { Ok(#table) }
+            },
+        },
+
+        Data::Enum(data) => pop_enum(data, &container)?,
+
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "unions can't be popped off the Lua stack",
+            ))
+        },
+    };
+
+    let name = &input.ident;
+    let bound: Path = parse_quote!(#krate::Poppable);
+    let generics = add_trait_bounds(input.generics.clone(), &bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::Poppable for #name #ty_generics
+            #where_clause
+        {
+            unsafe fn pop(
+                lstate: *mut #krate::ffi::lua_State,
+            ) -> ::std::result::Result<Self, #krate::Error> {
+                let top = #krate::ffi::lua_gettop(lstate);
+
+                if top == 0 {
+                    return Err(#krate::Error::PopEmptyStack);
+                }
+
+                // The value is always popped, even if it's invalid, along
+                // with anything pushed while reading its fields.
+                let res = (|| -> ::std::result::Result<Self, #krate::Error> {
+                    #body
+                })();
+
+                if res.is_err() {
+                    #krate::ffi::lua_settop(lstate, top - 1);
+                }
+
+                res
+            }
+        }
+    })
+}
+
+/// Pops an enum. Unit variants are expected to be strings, all the others
+/// tables with a single key-value pair mapping the variant's tag to its
+/// fields.
+fn pop_enum(
+    data: &DataEnum,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+
+    let mut unit_arms = Vec::new();
+    let mut unit_tags = Vec::new();
+    let mut table_arms = Vec::new();
+    let mut table_tags = Vec::new();
+
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(variant, Namespace::Lua)?;
+        let tag = attrs::variant_tag(variant, &attrs, container);
+        let tag_bytes = LitByteStr::new(tag.as_bytes(), Span::call_site());
+        let ident = &variant.ident;
+
+        match &variant.fields {
+            Fields::Unit => {
+                unit_arms.push(quote! { #tag_bytes => Self::#ident, });
+                unit_tags.push(format!("`{tag}`"));
+            },
+
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                let ty = &unnamed.unnamed[0].ty;
+                table_arms.push(quote! {
+                    #tag_bytes => Self::#ident(
+                        <#ty as #krate::Poppable>::pop(lstate)
+                            .map_err(|err| err.in_field(#tag))?
+                    ),
+                });
+                table_tags.push(format!("`{tag}`"));
+            },
+
+            fields => {
+                let table =
+                    pop_table(quote!(Self::#ident), fields, container)?;
+                // The table is popped in a closure so that we can add the
+                // variant's tag to the path of any error.
+                table_arms.push(quote! {
+                    #tag_bytes => (|| -> ::std::result::Result<
+                        Self,
+                        #krate::Error,
+                    > { Ok(#table) })()
+                    .map_err(|err| err.in_field(#tag))?,
+                });
+                table_tags.push(format!("`{tag}`"));
+            },
+        }
+    }
+
+    let unknown_variant = |tags: Vec<String>| {
+        let expected = format!("expected one of {}", tags.join(", "));
+        quote! {
+            other => {
+                return Err(#krate::Error::pop_error(
+                    ::std::any::type_name::<Self>(),
+                    ::std::format!(
+                        "unknown variant `{}`, {}",
+                        ::std::string::String::from_utf8_lossy(other),
+                        #expected,
+                    ),
+                ))
+            },
+        }
+    };
+
+    let expected_ty = if table_arms.is_empty() && !unit_arms.is_empty() {
+        quote!(#krate::ffi::LUA_TSTRING)
+    } else {
+        quote!(#krate::ffi::LUA_TTABLE)
+    };
+
+    let string_arm = (!unit_arms.is_empty()).then(|| {
+        let unknown = unknown_variant(unit_tags);
+        quote! {
+            #krate::ffi::LUA_TSTRING => {
+                let mut len = 0;
+                let ptr = #krate::ffi::lua_tolstring(lstate, -1, &mut len);
+                let tag = ::std::slice::from_raw_parts(ptr as *const u8, len);
+
+                let this = match tag {
+                    #(#unit_arms)*
+                    #unknown
+                };
+
+                #krate::ffi::lua_pop(lstate, 1);
+                Ok(this)
+            },
+        }
+    });
+
+    let table_arm = (!table_arms.is_empty()).then(|| {
+        let unknown = unknown_variant(table_tags);
+        quote! {
+            #krate::ffi::LUA_TTABLE => {
+                #krate::ffi::lua_pushnil(lstate);
+
+                if #krate::ffi::lua_next(lstate, -2) == 0 {
+                    return Err(#krate::Error::pop_error(
+                        ::std::any::type_name::<Self>(),
+                        "expected a table with a single key, found an empty \
+                         table instead",
+                    ));
+                }
+
+                match #krate::ffi::lua_type(lstate, -2) {
+                    #krate::ffi::LUA_TSTRING => {},
+                    _ => {
+                        let err = #krate::Error::pop_wrong_value::<Self>(
+                            lstate,
+                            -2,
+                            #krate::ffi::LUA_TSTRING,
+                        );
+                        #krate::ffi::lua_pop(lstate, 2);
+                        return Err(err);
+                    },
+                }
+
+                let mut len = 0;
+                let ptr = #krate::ffi::lua_tolstring(lstate, -2, &mut len);
+                let tag = ::std::slice::from_raw_parts(ptr as *const u8, len);
+
+                // This pops the value, leaving the key on the stack for the
+                // next call to `lua_next`.
+                let this = match tag {
+                    #(#table_arms)*
+                    #unknown
+                };
+
+                if #krate::ffi::lua_next(lstate, -2) != 0 {
+                    #krate::ffi::lua_pop(lstate, 2);
+                    return Err(#krate::Error::pop_error(
+                        ::std::any::type_name::<Self>(),
+                        "expected a table with a single key",
+                    ));
+                }
+
+                #krate::ffi::lua_pop(lstate, 1);
+                Ok(this)
+            },
+        }
+    });
+
+    Ok(quote! {
+        match #krate::ffi::lua_type(lstate, -1) {
+            #string_arm
+            #table_arm
+            _ => Err(#krate::Error::pop_wrong_value::<Self>(
+                lstate,
+                -1,
+                #expected_ty,
+            )),
+        }
+    })
+}
+
+/// Returns an expression popping the table at the top of the stack and
+/// building a `constructor { .. }` or `constructor(..)` from its fields.
+fn pop_table(
+    constructor: TokenStream,
+    fields: &Fields,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+
+    let pops = fields
+        .iter()
+        .zip(&bindings)
+        .enumerate()
+        .map(|(i, (field, binding))| {
+            let attrs = FieldAttrs::parse(field, Namespace::Lua)?;
+
+            let (get, in_path) = match &field.ident {
+                Some(_) => {
+                    let key = attrs::field_key(field, &attrs, container);
+                    let in_path = quote! { .in_field(#key) };
+
+                    if key.contains('\0') {
+                        return Err(Error::new_spanned(
+                            field,
+                            "field keys can't contain null bytes",
+                        ));
+                    }
+
+                    let key =
+                        LitStr::new(&format!("{key}\0"), Span::call_site());
+
+                    let get = quote! {
+                        #krate::ffi::lua_getfield(
+                            lstate,
+                            -1,
+                            #key.as_ptr() as *const ::std::ffi::c_char,
+                        );
+                    };
+
+                    (get, in_path)
+                },
+
+                None => {
+                    let idx = (i + 1) as i32;
+                    let get = quote! {
+                        #krate::ffi::lua_rawgeti(lstate, -1, #idx);
+                    };
+                    let in_path = quote! { .at_index(#idx as i64) };
+                    (get, in_path)
+                },
+            };
+
+            let ty = &field.ty;
+            let pop = quote! {
+                <#ty as #krate::Poppable>::pop(lstate)
+                    .map_err(|err| err #in_path)?
+            };
+
+            let value = match attrs.default {
+                None => pop,
+
+                Some(default) => {
+                    let default = match default {
+                        FieldDefault::Trait => {
+                            quote!(::std::default::Default::default())
+                        },
+                        FieldDefault::Path(path) => quote!(#path()),
+                    };
+
+                    quote! {
+                        match #krate::ffi::lua_type(lstate, -1) {
+                            #krate::ffi::LUA_TNIL => {
+                                #krate::ffi::lua_pop(lstate, 1);
+                                #default
+                            },
+                            _ => #pop,
+                        }
+                    }
+                },
+            };
+
+            Ok(quote! {
+                #get
+                let #binding: #ty = #value;
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let construct = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote! { #constructor { #(#idents: #bindings),* } }
+        },
+        _ => quote! { #constructor(#(#bindings),*) },
+    };
+
+    Ok(quote! {{
+        match #krate::ffi::lua_type(lstate, -1) {
+            #krate::ffi::LUA_TTABLE => {},
+            _ => {
+                return Err(#krate::Error::pop_wrong_value::<Self>(
+                    lstate,
+                    -1,
+                    #krate::ffi::LUA_TTABLE,
+                ))
+            },
+        }
+
+        #(#pops)*
+
+        // Pop the table.
+        #krate::ffi::lua_pop(lstate, 1);
+
+        #construct
+    }})
+}
@@ -0,0 +1,213 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, Fields, Path};
+
+use crate::attrs::{
+    self,
+    ContainerAttrs,
+    FieldAttrs,
+    Namespace,
+    VariantAttrs,
+};
+use crate::utils::{add_trait_bounds, bindings};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = ContainerAttrs::parse(&input.attrs, Namespace::Lua)?;
+    let krate = &container.krate;
+
+    let body = match &input.data {
+        Data::Struct(data) => push_struct(&data.fields, &container)?,
+
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let attrs = VariantAttrs::parse(variant, Namespace::Lua)?;
+                    let tag = attrs::variant_tag(variant, &attrs, &container);
+                    push_variant(
+                        &variant.ident,
+                        &variant.fields,
+                        &tag,
+                        &container,
+                    )
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            quote! { match self { #(#arms)* } }
+        },
+
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "unions can't be pushed onto the Lua stack",
+            ))
+        },
+    };
+
+    let name = &input.ident;
+    let bound: Path = parse_quote!(#krate::Pushable);
+    let generics = add_trait_bounds(input.generics.clone(), &bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::Pushable for #name #ty_generics
+            #where_clause
+        {
+            unsafe fn push(
+                self,
+                lstate: *mut #krate::ffi::lua_State,
+            ) -> ::std::result::Result<::std::ffi::c_int, #krate::Error> {
+                // If pushing a field fails the half-built table is popped.
+                #krate::push_or_restore(lstate, move || { #body })
+            }
+        }
+    })
+}
+
+/// Pushes a struct as a table, an array table or `nil` depending on the kind
+/// of its fields.
+fn push_struct(
+    fields: &Fields,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+
+    Ok(match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            let table = push_table(fields, &bindings, container)?;
+            quote! {
+                let Self { #(#idents: #bindings),* } = self;
+                #table
+                Ok(1)
+            }
+        },
+
+        Fields::Unnamed(_) => {
+            let array = push_array(krate, &bindings);
+            quote! {
+                let Self(#(#bindings),*) = self;
+                #array
+                Ok(1)
+            }
+        },
+
+        Fields::Unit => quote! {
+            #krate::ffi::lua_pushnil(lstate);
+            Ok(1)
+        },
+    })
+}
+
+/// Pushes an enum variant. Unit variants are pushed as strings, all the
+/// others as tables with a single key-value pair mapping the variant's tag to
+/// its fields.
+fn push_variant(
+    ident: &Ident,
+    fields: &Fields,
+    tag: &str,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+    let tag_len = tag.len();
+
+    let (pattern, value) = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            let table = push_table(fields, &bindings, container)?;
+            (quote! { Self::#ident { #(#idents: #bindings),* } }, table)
+        },
+
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let binding = &bindings[0];
+            (
+                quote! { Self::#ident(#binding) },
+                quote! { #krate::push_one(#binding, lstate)?; },
+            )
+        },
+
+        Fields::Unnamed(_) => (
+            quote! { Self::#ident(#(#bindings),*) },
+            push_array(krate, &bindings),
+        ),
+
+        Fields::Unit => {
+            return Ok(quote! {
+                Self::#ident => {
+                    #krate::ffi::lua_pushlstring(
+                        lstate,
+                        #tag.as_ptr() as *const ::std::ffi::c_char,
+                        #tag_len,
+                    );
+                    Ok(1)
+                },
+            });
+        },
+    };
+
+    Ok(quote! {
+        #pattern => {
+            #krate::ffi::lua_createtable(lstate, 0, 1);
+            #krate::ffi::lua_pushlstring(
+                lstate,
+                #tag.as_ptr() as *const ::std::ffi::c_char,
+                #tag_len,
+            );
+            #value
+            #krate::ffi::lua_rawset(lstate, -3);
+            Ok(1)
+        },
+    })
+}
+
+/// Pushes a new table containing the given named fields.
+fn push_table(
+    fields: &Fields,
+    bindings: &[Ident],
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let len = fields.len() as i32;
+
+    let sets = fields
+        .iter()
+        .zip(bindings)
+        .map(|(field, binding)| {
+            let attrs = FieldAttrs::parse(field, Namespace::Lua)?;
+            let key = attrs::field_key(field, &attrs, container);
+            let key_len = key.len();
+
+            Ok(quote! {
+                #krate::ffi::lua_pushlstring(
+                    lstate,
+                    #key.as_ptr() as *const ::std::ffi::c_char,
+                    #key_len,
+                );
+                #krate::push_one(#binding, lstate)?;
+                #krate::ffi::lua_rawset(lstate, -3);
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #krate::ffi::lua_createtable(lstate, 0, #len);
+        #(#sets)*
+    })
+}
+
+/// Pushes a new array table containing the given unnamed fields.
+fn push_array(krate: &Path, bindings: &[Ident]) -> TokenStream {
+    let len = bindings.len() as i32;
+    let indices = 1..=len;
+
+    quote! {
+        #krate::ffi::lua_createtable(lstate, #len, 0);
+        #(
+            #krate::push_one(#bindings, lstate)?;
+            #krate::ffi::lua_rawseti(lstate, -2, #indices);
+        )*
+    }
+}
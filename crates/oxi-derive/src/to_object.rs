@@ -0,0 +1,263 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, Field, Fields, Path};
+
+use crate::attrs::{
+    self,
+    ContainerAttrs,
+    FieldAttrs,
+    Namespace,
+    VariantAttrs,
+};
+use crate::utils::{add_trait_bounds, bindings, is_option};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = ContainerAttrs::parse(&input.attrs, Namespace::Object)?;
+    let krate = &container.krate;
+
+    let body = match &input.data {
+        Data::Struct(data) => struct_to_object(&data.fields, &container)?,
+
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let attrs =
+                        VariantAttrs::parse(variant, Namespace::Object)?;
+                    let tag = attrs::variant_tag(variant, &attrs, &container);
+                    variant_to_object(
+                        &variant.ident,
+                        &variant.fields,
+                        &tag,
+                        &container,
+                    )
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            quote! { match self { #(#arms)* } }
+        },
+
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "unions can't be converted into Objects",
+            ))
+        },
+    };
+
+    let name = &input.ident;
+    let bound: Path = parse_quote!(#krate::conversion::ToObject);
+    let generics = add_trait_bounds(input.generics.clone(), &bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::conversion::ToObject for #name #ty_generics
+            #where_clause
+        {
+            fn to_object(
+                self,
+            ) -> ::std::result::Result<
+                #krate::Object,
+                #krate::conversion::Error,
+            > {
+                #body
+            }
+        }
+    })
+}
+
+/// Converts a struct into a dictionary, an array or `nil` depending on the
+/// kind of its fields.
+fn struct_to_object(
+    fields: &Fields,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+
+    Ok(match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            let dict = to_dict(fields, &bindings, container)?;
+            quote! {
+                let Self { #(#idents: #bindings),* } = self;
+                Ok(#dict.into())
+            }
+        },
+
+        Fields::Unnamed(_) => {
+            let array = to_array(fields, &bindings, container)?;
+            quote! {
+                let Self(#(#bindings),*) = self;
+                Ok(#array.into())
+            }
+        },
+
+        Fields::Unit => quote! { Ok(#krate::Object::nil()) },
+    })
+}
+
+/// Converts an enum variant. Unit variants are converted into strings, all
+/// the others into dictionaries with a single key-value pair mapping the
+/// variant's tag to its fields.
+fn variant_to_object(
+    ident: &Ident,
+    fields: &Fields,
+    tag: &str,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let bindings = bindings(fields);
+
+    let (pattern, value) = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            let dict = to_dict(fields, &bindings, container)?;
+            (
+                quote! { Self::#ident { #(#idents: #bindings),* } },
+                quote! { #krate::Object::from(#dict) },
+            )
+        },
+
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let field = &unnamed.unnamed[0];
+            let binding = &bindings[0];
+            (
+                quote! { Self::#ident(#binding) },
+                field_to_object(field, binding, krate),
+            )
+        },
+
+        Fields::Unnamed(_) => {
+            let array = to_array(fields, &bindings, container)?;
+            (
+                quote! { Self::#ident(#(#bindings),*) },
+                quote! { #krate::Object::from(#array) },
+            )
+        },
+
+        Fields::Unit => {
+            return Ok(quote! {
+                Self::#ident => Ok(#krate::Object::from(#tag)),
+            })
+        },
+    };
+
+    Ok(quote! {
+        #pattern => {
+            let mut __dict = #krate::Dictionary::with_capacity(1);
+            __dict.push(#krate::KeyValuePair::from((#tag, #value)));
+            Ok(__dict.into())
+        },
+    })
+}
+
+/// Returns an expression building a dictionary from the given named fields.
+///
+/// Fields whose value is `nil` are left out, like when collecting into a
+/// `Dictionary`.
+fn to_dict(
+    fields: &Fields,
+    bindings: &[Ident],
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+    let mut len = 0usize;
+
+    let sets = fields
+        .iter()
+        .zip(bindings)
+        .map(|(field, binding)| {
+            let attrs = FieldAttrs::parse(field, Namespace::Object)?;
+            let value = field_to_object(field, binding, krate);
+
+            if attrs.skip {
+                return Ok(quote! {});
+            }
+
+            if attrs.flatten {
+                return Ok(quote! {
+                    let __obj = #value;
+                    if !__obj.is_nil() {
+                        let __flat = <#krate::Dictionary as
+                            #krate::conversion::FromObject>::from_object(
+                            __obj,
+                        )?;
+                        __dict.extend(
+                            __flat.into_iter().map(#krate::KeyValuePair::from),
+                        );
+                    }
+                });
+            }
+
+            len += 1;
+            let key = attrs::field_key(field, &attrs, container);
+
+            Ok(quote! {
+                let __obj = #value;
+                if !__obj.is_nil() {
+                    __dict.push(#krate::KeyValuePair::from((#key, __obj)));
+                }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {{
+        let mut __dict = #krate::Dictionary::with_capacity(#len);
+        #(#sets)*
+        __dict
+    }})
+}
+
+/// Returns an expression building an array from the given unnamed fields.
+fn to_array(
+    fields: &Fields,
+    bindings: &[Ident],
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let krate = &container.krate;
+
+    let pushes = fields
+        .iter()
+        .zip(bindings)
+        .map(|(field, binding)| {
+            let attrs = FieldAttrs::parse(field, Namespace::Object)?;
+            Ok((!attrs.skip).then(|| field_to_object(field, binding, krate)))
+        })
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let len = pushes.len();
+
+    Ok(quote! {{
+        let mut __array = #krate::Array::with_capacity(#len);
+        #( __array.push(#pushes); )*
+        __array
+    }})
+}
+
+/// Returns an expression converting the field bound to `binding` into an
+/// `Object`.
+fn field_to_object(
+    field: &Field,
+    binding: &Ident,
+    krate: &Path,
+) -> TokenStream {
+    // `Option<T>` only implements `ToObject` if `T: Into<Object>`, so we
+    // unwrap it here to also support types that only implement `ToObject`.
+    if is_option(&field.ty) {
+        quote! {
+            match #binding {
+                ::std::option::Option::Some(__value) => {
+                    #krate::conversion::ToObject::to_object(__value)?
+                },
+                ::std::option::Option::None => #krate::Object::nil(),
+            }
+        }
+    } else {
+        quote! { #krate::conversion::ToObject::to_object(#binding)? }
+    }
+}
@@ -0,0 +1,38 @@
+use proc_macro2::Ident;
+use quote::format_ident;
+use syn::{Fields, GenericParam, Generics, Path, Type};
+
+/// Adds a `T: bound` bound to every type parameter `T`.
+pub(crate) fn add_trait_bounds(
+    mut generics: Generics,
+    bound: &Path,
+) -> Generics {
+    for param in &mut generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(syn::parse_quote!(#bound));
+        }
+    }
+    generics
+}
+
+/// Returns the identifiers the fields get bound to in the generated code.
+///
+/// These are prefixed with `__` so that they can't shadow the `lstate`
+/// argument.
+pub(crate) fn bindings(fields: &Fields) -> Vec<Ident> {
+    (0..fields.len()).map(|i| format_ident!("__field{}", i)).collect()
+}
+
+/// Returns whether `ty` is an `Option`.
+///
+/// This only looks at the last segment of the path, so it can be fooled by
+/// type aliases or by other types called `Option`.
+pub(crate) fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) if ty.qself.is_none() => matches!(
+            ty.path.segments.last(),
+            Some(segment) if segment.ident == "Option"
+        ),
+        _ => false,
+    }
+}
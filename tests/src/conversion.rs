@@ -0,0 +1,73 @@
+use nvim_oxi::api::{self, Buffer, TabPage, Window};
+use nvim_oxi::conversion::{self, FromObject, ToObject};
+use nvim_oxi::{self as oxi, Dictionary, Object};
+
+#[derive(Debug, PartialEq, FromObject, ToObject)]
+#[object(rename_all = "camelCase")]
+struct Layout {
+    buffer: Buffer,
+
+    windows: Vec<Window>,
+
+    #[object(rename = "tab")]
+    tabpage: Option<TabPage>,
+
+    #[object(default)]
+    zoomed: bool,
+
+    #[object(skip)]
+    is_dirty: bool,
+
+    #[object(flatten)]
+    extra: Dictionary,
+}
+
+#[oxi::test]
+fn derive_handles_roundtrip() {
+    let layout = Layout {
+        buffer: api::get_current_buf(),
+        windows: vec![api::get_current_win()],
+        tabpage: Some(api::get_current_tabpage()),
+        zoomed: true,
+        is_dirty: true,
+        extra: Dictionary::from_iter([("splitRight", true)]),
+    };
+
+    api::set_var("layout", layout).unwrap();
+
+    let layout = api::get_var::<Layout>("layout").unwrap();
+    assert_eq!(api::get_current_buf(), layout.buffer);
+    assert_eq!(vec![api::get_current_win()], layout.windows);
+    assert_eq!(Some(api::get_current_tabpage()), layout.tabpage);
+    assert!(layout.zoomed);
+    assert!(!layout.is_dirty);
+    assert_eq!(Some(&Object::from(true)), layout.extra.get("splitRight"));
+}
+
+#[oxi::test]
+fn derive_missing_field() {
+    api::command("let g:layout = { 'windows': [] }").unwrap();
+
+    let obj = api::get_var::<Object>("layout").unwrap();
+    assert_eq!(
+        Err(conversion::Error::MissingField { field: "buffer" }),
+        Layout::from_object(obj)
+    );
+}
+
+#[oxi::test]
+fn derive_to_object_skips_nil() {
+    let layout = Layout {
+        buffer: Buffer::current(),
+        windows: Vec::new(),
+        tabpage: None,
+        zoomed: false,
+        is_dirty: false,
+        extra: Dictionary::new(),
+    };
+
+    let dict = Dictionary::from_object(layout.to_object().unwrap()).unwrap();
+    assert!(!dict.contains_key("tab"));
+    assert!(!dict.contains_key("isDirty"));
+    assert!(dict.contains_key("zoomed"));
+}
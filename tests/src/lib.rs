@@ -1 +1,3 @@
 mod api;
+mod conversion;
+mod lua;
@@ -0,0 +1,107 @@
+use nvim_oxi::lua::{self, ffi::*, Poppable, Pushable, StackGuard};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn pop_borrowed_str() {
+    unsafe {
+        lua::with_state(|lstate| {
+            "baz".push(lstate).unwrap();
+            Option::<&str>::None.push(lstate).unwrap();
+            b"bar\xff".as_slice().push(lstate).unwrap();
+            "foo".push(lstate).unwrap();
+
+            let guard = StackGuard::new(lstate);
+            let string: &str = guard.pop().unwrap();
+            let bytes: &[u8] = guard.pop().unwrap();
+            let none: Option<&[u8]> = guard.pop().unwrap();
+            let some: Option<&str> = guard.pop().unwrap();
+
+            // Force a collection to check the strings are still alive.
+            lua::eval::<()>("collectgarbage()").unwrap();
+
+            assert_eq!("foo", string);
+            assert_eq!(b"bar\xff", bytes);
+            assert_eq!(None, none);
+            assert_eq!(Some("baz"), some);
+            assert_eq!(0, lua_gettop(lstate));
+        })
+    }
+}
+
+#[oxi::test]
+fn pop_borrowed_invalid_utf8() {
+    unsafe {
+        lua::with_state(|lstate| {
+            b"\xff".as_slice().push(lstate).unwrap();
+            let guard = StackGuard::new(lstate);
+            assert!(guard.pop::<&str>().is_err());
+            lua_settop(lstate, 0);
+        })
+    }
+}
+
+#[oxi::test]
+fn vec_u8_is_a_string() {
+    let bytes = vec![0, 159, 146, 150];
+
+    let len = lua::eval::<Function<Vec<u8>, (String, usize)>>(
+        "function(s) return type(s), #s end",
+    )
+    .unwrap();
+    assert_eq!(Ok(("string".into(), 4)), len.call(bytes.clone()));
+
+    let id =
+        lua::eval::<Function<Vec<u8>, Vec<u8>>>("function(s) return s end")
+            .unwrap();
+    assert_eq!(Ok(bytes), id.call(vec![0, 159, 146, 150]));
+
+    // Same for arrays and slices.
+    let kind = lua::eval::<Function<[u8; 2], String>>("type").unwrap();
+    assert_eq!(Ok("string".into()), kind.call([0, 255]));
+
+    let kind = lua::eval::<Function<&[u8], String>>("type").unwrap();
+    assert_eq!(Ok("string".into()), kind.call(b"\xff".as_slice()));
+
+    // Tables of bytes can still be popped.
+    assert_eq!(Ok(vec![1, 2, 3]), lua::eval::<Vec<u8>>("{ 1, 2, 3 }"));
+
+    // But other vectors can't be popped from strings.
+    assert!(unsafe {
+        lua::with_state(|lstate| {
+            "foo".push(lstate).unwrap();
+            let res = <Vec<u32> as Poppable>::pop(lstate);
+            lua_settop(lstate, 0);
+            res
+        })
+    }
+    .is_err());
+}
+
+#[oxi::test]
+fn function_borrowed_args() {
+    let len = Function::<(&str, Option<&[u8]>), usize>::from_fn_borrowed(
+        |(string, bytes): (&str, Option<&[u8]>)| {
+            Ok::<_, oxi::Error>(string.len() + bytes.map_or(0, <[u8]>::len))
+        },
+    );
+
+    assert_eq!(Ok(3), len.call(("foo", None)));
+    assert_eq!(Ok(5), len.call(("foo", Some(b"\xff\xff".as_slice()))));
+
+    // Called from Lua, where the string could be huge.
+    let call = lua::eval::<
+        Function<Function<(&str, Option<&[u8]>), usize>, usize>,
+    >("function(f) return f(string.rep('a', 1024 * 1024)) end")
+    .unwrap();
+    assert_eq!(Ok(1024 * 1024), call.call(len.clone()));
+
+    // Invalid UTF-8 can't be borrowed as a `&str`.
+    let utf8 = lua::eval::<Function<Function<&str, ()>, ()>>(
+        "function(f) return f('\\255') end",
+    )
+    .unwrap();
+    let noop = Function::<&str, ()>::from_fn_borrowed(|_: &str| {
+        Ok::<_, oxi::Error>(())
+    });
+    assert!(utf8.call(noop).is_err());
+}
@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use nvim_oxi::lua::{self, ffi::*, Poppable, Pushable};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn maps_and_sets() {
+    let map = lua::eval::<BTreeMap<String, u8>>("{ a = 1, b = 2 }").unwrap();
+    assert_eq!(BTreeMap::from([("a".into(), 1), ("b".into(), 2)]), map);
+
+    let set =
+        lua::eval::<BTreeSet<String>>("{ foo = true, bar = false }").unwrap();
+    assert_eq!(BTreeSet::from(["foo".into()]), set);
+
+    let count = lua::eval::<Function<HashSet<&str>, (usize, bool)>>(
+        "function(s)
+            local n = 0
+            for _, v in pairs(s) do n = n + (v == true and 1 or 0) end
+            return n, s.foo
+        end",
+    )
+    .unwrap();
+    assert_eq!(Ok((2, true)), count.call(HashSet::from(["foo", "bar"])));
+
+    let get = lua::eval::<Function<HashMap<&str, u8>, u8>>(
+        "function(m) return m.foo end",
+    )
+    .unwrap();
+    assert_eq!(Ok(42), get.call(HashMap::from([("foo", 42)])));
+}
+
+#[oxi::test]
+fn arrays_boxes_and_cows() {
+    assert_eq!(Ok([1, 2, 3]), lua::eval::<[u8; 3]>("{ 1, 2, 3 }"));
+    assert!(lua::eval::<[u8; 2]>("{ 1, 2, 3 }").is_err());
+
+    let id = lua::eval::<Function<[i32; 2], Box<Vec<u8>>>>(
+        "function(t) return { t[2], t[1] } end",
+    )
+    .unwrap();
+    assert_eq!(Ok(Box::new(vec![2, 1])), id.call([1, 2]));
+
+    // Arrays of bytes are strings, like `Vec<u8>`s.
+    let rev =
+        lua::eval::<Function<[u8; 2], [u8; 2]>>("string.reverse").unwrap();
+    assert_eq!(Ok([2, 1]), rev.call([1, 2]));
+
+    let upper = lua::eval::<Function<Cow<str>, Cow<str>>>(
+        "function(s) return s:upper() end",
+    )
+    .unwrap();
+    assert_eq!(Ok(Cow::Borrowed("FOO")), upper.call(Cow::Borrowed("foo")));
+}
+
+#[oxi::test]
+fn result_nil_err() {
+    let open = lua::eval::<Function<bool, Result<u8, String>>>(
+        "function(ok) if ok then return 1 else return nil, 'oops' end end",
+    )
+    .unwrap();
+    assert_eq!(Ok(Ok(1)), open.call(true));
+    assert_eq!(Ok(Err("oops".into())), open.call(false));
+
+    // A `nil` below the returned value isn't mistaken for an error.
+    unsafe { lua::with_state(|lstate| lua_pushnil(lstate)) };
+    assert_eq!(Ok(Ok(1)), open.call(true));
+    unsafe {
+        lua::with_state(|lstate| {
+            assert_eq!(1, lua_gettop(lstate));
+            lua_pop(lstate, 1);
+        })
+    };
+
+    unsafe {
+        lua::with_state(|lstate| {
+            assert_eq!(Ok(1), Ok::<u8, &str>(7).push(lstate));
+            assert_eq!(Ok(2), Err::<u8, &str>("oops").push(lstate));
+            assert_eq!(3, lua_gettop(lstate));
+
+            // Only the last two values are popped.
+            assert_eq!(
+                Ok(Err("oops".into())),
+                Result::<u8, String>::pop(lstate)
+            );
+            assert_eq!(Ok(Ok(7)), Result::<u8, String>::pop(lstate));
+            assert_eq!(0, lua_gettop(lstate));
+        })
+    }
+}
+
+#[oxi::test]
+fn nested_results() {
+    unsafe {
+        lua::with_state(|lstate| {
+            let ok = vec![Ok::<u8, &str>(1), Ok(2)];
+            assert_eq!(Ok(1), ok.push(lstate));
+            assert_eq!(Ok(vec![1, 2]), <Vec<u8> as Poppable>::pop(lstate));
+
+            let err = vec![Ok::<u8, &str>(1), Err("oops")];
+            assert!(matches!(
+                err.push(lstate),
+                Err(lua::Error::PushError { .. })
+            ));
+
+            let err = HashMap::from([("foo", Err::<u8, &str>("oops"))]);
+            assert!(matches!(
+                err.push(lstate),
+                Err(lua::Error::PushError { .. })
+            ));
+
+            lua_settop(lstate, 0);
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use nvim_oxi::lua::{self, Coroutine, CoroutineStatus, Yielding};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn coroutine_resume_from_rust() {
+    let fun = lua::eval::<Function<i32, i32>>(
+        "function(a) local b = coroutine.yield(a + 1); return b * 2 end",
+    )
+    .unwrap();
+
+    let co = Coroutine::new(fun).unwrap();
+    assert_eq!(CoroutineStatus::Suspended, co.status());
+
+    assert_eq!(Ok(2), co.resume::<_, i32>(1));
+    assert_eq!(CoroutineStatus::Suspended, co.status());
+
+    assert_eq!(Ok(10), co.resume::<_, i32>(5));
+    assert_eq!(CoroutineStatus::Dead, co.status());
+
+    assert!(co.resume::<_, ()>(()).is_err());
+}
+
+#[oxi::test]
+fn coroutine_error() {
+    let co = lua::eval::<Coroutine>(
+        "coroutine.create(function() error('oops', 0) end)",
+    )
+    .unwrap();
+
+    assert_eq!(
+        Err(lua::Error::runtime_error("oops")),
+        co.resume::<_, ()>(())
+    );
+    assert_eq!(CoroutineStatus::Dead, co.status());
+}
+
+#[oxi::test]
+fn coroutine_new_from_non_function() {
+    assert!(Coroutine::new(42).is_err());
+}
+
+#[oxi::test]
+fn rust_function_yields_to_lua() {
+    let fun = Function::<i32, i32>::from_fn_yielding(|n| {
+        Ok::<_, oxi::Error>(if n > 0 {
+            Yielding::Yield(n)
+        } else {
+            Yielding::Return(0)
+        })
+    });
+
+    let run = lua::load::<Function<i32, i32>, (i32, i32)>(
+        "local f = ...
+         local co = coroutine.create(function() return f(1) + f(0) end)
+         local _, yielded = coroutine.resume(co)
+         local _, returned = coroutine.resume(co, 41)
+         return yielded, returned",
+        "=run",
+    )
+    .unwrap();
+
+    assert_eq!(Ok((1, 41)), run.call(fun));
+}
@@ -0,0 +1,188 @@
+use nvim_oxi::lua::{self, ffi::*, Poppable, Pushable};
+use nvim_oxi::{self as oxi, api};
+
+#[derive(Debug, PartialEq, Pushable, Poppable)]
+struct Car {
+    manufacturer: Manufacturer,
+
+    miles: u32,
+
+    #[lua(rename = "issue")]
+    problem: Option<Problem>,
+
+    #[lua(default = "yep")]
+    works: bool,
+}
+
+fn yep() -> bool {
+    true
+}
+
+#[derive(Debug, PartialEq, Pushable, Poppable)]
+#[lua(rename_all = "snake_case")]
+enum Manufacturer {
+    Nikola,
+    Tesla,
+    #[lua(rename = "VW")]
+    Volkswagen,
+}
+
+#[derive(Debug, PartialEq, Pushable, Poppable)]
+#[lua(rename_all = "snake_case")]
+enum Problem {
+    DoesntMove,
+    FlatTyre(u8),
+    Leaking { fluid: String, liters: f32 },
+}
+
+#[derive(Debug, PartialEq, Pushable, Poppable)]
+struct Point(i32, i32);
+
+fn roundtrip<T: Pushable + Poppable>(value: T) -> Result<T, lua::Error> {
+    unsafe {
+        lua::with_state(|lstate| {
+            value.push(lstate)?;
+            T::pop(lstate)
+        })
+    }
+}
+
+fn pop_global<T: Poppable>(name: &str) -> Result<T, lua::Error> {
+    let name = std::ffi::CString::new(name).unwrap();
+    unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, name.as_ptr());
+            T::pop(lstate)
+        })
+    }
+}
+
+fn stack_height() -> i32 {
+    unsafe { lua::with_state(|lstate| lua_gettop(lstate)) }
+}
+
+#[oxi::test]
+fn derive_roundtrip_struct() {
+    let car = Car {
+        manufacturer: Manufacturer::Volkswagen,
+        miles: 42,
+        problem: Some(Problem::Leaking { fluid: "oil".into(), liters: 0.5 }),
+        works: false,
+    };
+
+    let expected = Car {
+        manufacturer: Manufacturer::Volkswagen,
+        miles: 42,
+        problem: Some(Problem::Leaking { fluid: "oil".into(), liters: 0.5 }),
+        works: false,
+    };
+
+    assert_eq!(Ok(expected), roundtrip(car));
+}
+
+#[oxi::test]
+fn derive_roundtrip_tuple_struct() {
+    assert_eq!(Ok(Point(1, -1)), roundtrip(Point(1, -1)));
+}
+
+#[oxi::test]
+fn derive_pop_from_lua() {
+    api::command(
+        "lua _G.car = { manufacturer = 'VW', miles = 7, issue = { flat_tyre \
+         = 2 } }",
+    )
+    .unwrap();
+
+    let car = pop_global::<Car>("car").unwrap();
+    assert_eq!(Manufacturer::Volkswagen, car.manufacturer);
+    assert_eq!(Some(Problem::FlatTyre(2)), car.problem);
+    assert!(car.works);
+}
+
+#[oxi::test]
+fn derive_pop_unknown_variant() {
+    api::command("lua _G.manufacturer = 'ford'").unwrap();
+
+    // The string is popped even if it's not a valid variant.
+    let height = stack_height();
+    assert!(pop_global::<Manufacturer>("manufacturer").is_err());
+    assert_eq!(height, stack_height());
+
+    api::command("lua _G.car = { manufacturer = 'tesla', miles = -1 }")
+        .unwrap();
+
+    // Same for the table and its fields.
+    let height = stack_height();
+    assert!(pop_global::<Car>("car").is_err());
+    assert_eq!(height, stack_height());
+}
+
+#[derive(Debug, PartialEq, Pushable)]
+#[lua(rename_all = "PascalCase")]
+struct Unit {
+    empty: (),
+}
+
+#[oxi::test]
+fn derive_push_single_value_fields() {
+    let res = unsafe {
+        lua::with_state(|lstate| {
+            let top = lua_gettop(lstate);
+            let res = Unit { empty: () }.push(lstate);
+            lua_settop(lstate, top);
+            res
+        })
+    };
+
+    assert!(matches!(res, Err(lua::Error::PushError { .. })), "{res:?}");
+}
+
+#[derive(Debug, PartialEq, Pushable)]
+struct Wrapper {
+    point: Point,
+    unit: Unit,
+}
+
+#[oxi::test]
+fn derive_push_error_restores_stack() {
+    let height = stack_height();
+
+    let res = unsafe {
+        lua::with_state(|lstate| {
+            Wrapper { point: Point(1, 2), unit: Unit { empty: () } }
+                .push(lstate)
+        })
+    };
+    assert!(res.is_err());
+    assert_eq!(height, stack_height());
+
+    // Same for a failing element of an array.
+    let res = unsafe {
+        lua::with_state(|lstate| vec![Unit { empty: () }].push(lstate))
+    };
+    assert!(res.is_err());
+    assert_eq!(height, stack_height());
+}
+
+#[oxi::test]
+fn derive_pop_error_path() {
+    api::command(
+        "lua _G.cars = { { manufacturer = 'tesla', miles = 1 }, { \
+         manufacturer = 'tesla', miles = 2, issue = { leaking = { fluid = \
+         'oil', liters = 'a lot' } } } }",
+    )
+    .unwrap();
+
+    let err = pop_global::<Vec<Car>>("cars").unwrap_err();
+
+    match &err {
+        lua::Error::PopError { path, .. } => {
+            assert_eq!("[2].issue.leaking.liters", path.to_string())
+        },
+        other => panic!("expected a PopError, got {other:?}"),
+    }
+
+    assert!(err.to_string().contains("at `[2].issue.leaking.liters`"));
+
+    unsafe { lua::with_state(|lstate| lua_settop(lstate, 0)) };
+}
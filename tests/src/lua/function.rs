@@ -0,0 +1,86 @@
+use nvim_oxi::lua::{self, ffi::*, macros::cstr, Poppable, Variadic};
+use nvim_oxi::{self as oxi, api, Function};
+
+fn global_function(name: *const std::ffi::c_char) -> Function<(), ()> {
+    unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, name);
+            Function::pop(lstate).unwrap()
+        })
+    }
+}
+
+#[oxi::test]
+fn function_call_with_traceback() {
+    api::command("lua _G.f = function() error('boom') end").unwrap();
+    let f = global_function(cstr!("f"));
+
+    match f.call(()) {
+        Err(lua::Error::RuntimeError { message, traceback: None }) => {
+            assert!(message.ends_with("boom"), "{message}")
+        },
+        other => panic!("{other:?}"),
+    }
+
+    match f.call_with_traceback(()) {
+        Err(lua::Error::RuntimeError { message, traceback: Some(tb) }) => {
+            assert!(message.ends_with("boom"), "{message}");
+            assert!(tb.starts_with("stack traceback:"), "{tb}");
+        },
+        other => panic!("{other:?}"),
+    }
+}
+
+#[oxi::test]
+fn function_panic_becomes_lua_error() {
+    let f = Function::from_fn(|()| -> Result<(), oxi::Error> {
+        panic!("oh no");
+    });
+
+    match f.call(()) {
+        Err(lua::Error::RuntimeError { message, .. }) => {
+            assert!(message.contains("panicked at"), "{message}");
+            assert!(message.contains("oh no"), "{message}");
+        },
+        other => panic!("{other:?}"),
+    }
+}
+
+fn stack_height() -> i32 {
+    unsafe { lua::with_state(|lstate| lua_gettop(lstate)) }
+}
+
+#[oxi::test]
+fn function_call_only_pops_its_returns() {
+    api::command("lua _G.three = function() return 1, 2, 3 end").unwrap();
+    api::command("lua _G.none = function() end").unwrap();
+
+    let (three, last, none) = unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, cstr!("three"));
+            let three = Function::<(), Variadic<i32>>::pop(lstate).unwrap();
+            lua_getglobal(lstate, cstr!("three"));
+            let last = Function::<(), i32>::pop(lstate).unwrap();
+            lua_getglobal(lstate, cstr!("none"));
+            let none = Function::<(), ()>::pop(lstate).unwrap();
+            (three, last, none)
+        })
+    };
+
+    // Leave a value on the stack that doesn't belong to the calls.
+    unsafe { lua::with_state(|lstate| lua_pushinteger(lstate, 42)) };
+    let height = stack_height();
+
+    let nums = three.call(()).unwrap();
+    assert_eq!(vec![1, 2, 3], nums.into_inner());
+    assert_eq!(height, stack_height());
+
+    assert_eq!(Ok(()), none.call(()));
+    assert_eq!(height, stack_height());
+
+    // Extra returns are discarded.
+    assert!(last.call(()).is_ok());
+    assert_eq!(height, stack_height());
+
+    unsafe { lua::with_state(|lstate| lua_pop(lstate, 1)) };
+}
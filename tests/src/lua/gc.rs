@@ -0,0 +1,38 @@
+use nvim_oxi::lua::{self, gc, Table};
+use nvim_oxi as oxi;
+
+#[oxi::test]
+fn collect_frees_memory() {
+    gc::collect();
+    let before = gc::memory_used();
+
+    let table =
+        lua::eval::<Table>("(function() local t = {} for i = 1, 100000 do \
+                             t[i] = tostring(i) end return t end)()")
+            .unwrap();
+    assert!(gc::memory_used() > before + 1_000_000);
+
+    drop(table);
+    gc::collect();
+    assert!(gc::memory_used() < before + 100_000);
+}
+
+#[oxi::test]
+fn stop_and_restart() {
+    assert!(gc::is_running());
+    gc::stop();
+    assert!(!gc::is_running());
+    gc::restart();
+    assert!(gc::is_running());
+
+    while !gc::step(0) {}
+}
+
+#[oxi::test]
+fn tuning() {
+    let pause = gc::set_pause(150);
+    assert_eq!(150, gc::set_pause(pause));
+
+    let step_mul = gc::set_step_mul(300);
+    assert_eq!(300, gc::set_step_mul(step_mul));
+}
@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use nvim_oxi::lua::hook::{Budget, HookEvent, HookTriggers};
+use nvim_oxi::lua::{self, hook, Error};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn hook_events() {
+    let lines = Rc::new(RefCell::new(Vec::new()));
+
+    let f = lua::eval::<Function<(), ()>>(
+        "function()\n local a = 1\n local b = 2\nend",
+    )
+    .unwrap();
+
+    {
+        let lines = Rc::clone(&lines);
+        hook::set(HookTriggers { every_line: true, ..Default::default() }, {
+            move |event| {
+                if let HookEvent::Line(line) = event {
+                    lines.borrow_mut().push(line);
+                }
+                Ok(())
+            }
+        });
+    }
+
+    f.call(()).unwrap();
+    hook::remove();
+
+    assert_eq!(&[2, 3], &lines.borrow()[..2]);
+}
+
+#[oxi::test]
+fn hook_error_is_raised() {
+    let f = lua::eval::<Function<(), ()>>("function() end").unwrap();
+
+    hook::set(HookTriggers { on_calls: true, ..Default::default() }, |_| {
+        Err(Error::runtime_error("nope"))
+    });
+    let res = f.call(());
+    hook::remove();
+
+    assert!(res.is_err());
+    assert_eq!(Ok(()), f.call(()));
+}
+
+#[oxi::test]
+fn budget_instructions() {
+    let forever =
+        lua::eval::<Function<(), ()>>("function() while true do end end")
+            .unwrap();
+    assert_eq!(
+        Err(Error::Timeout),
+        forever.call_with_budget((), Budget::Instructions(100_000))
+    );
+
+    // Catching the error doesn't help.
+    let stubborn = lua::eval::<Function<(), ()>>(
+        "function() while true do pcall(function() while true do end end) \
+         end end",
+    )
+    .unwrap();
+    assert_eq!(
+        Err(Error::Timeout),
+        stubborn.call_with_budget((), Budget::Instructions(100_000))
+    );
+
+    let quick =
+        lua::eval::<Function<u32, u32>>("function(n) return n + 1 end")
+            .unwrap();
+    assert_eq!(Ok(2), quick.call_with_budget(1, Budget::Instructions(100)));
+}
+
+#[oxi::test]
+fn budget_time() {
+    let forever = lua::eval::<Function<(), ()>>(
+        "function() local i = 0 while true do i = i + 1 end end",
+    )
+    .unwrap();
+    assert_eq!(
+        Err(Error::Timeout),
+        forever.call_with_budget((), Budget::Time(Duration::from_millis(50)))
+    );
+}
+
+#[oxi::test]
+fn hook_replaces_itself() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let triggers = HookTriggers { on_calls: true, ..Default::default() };
+
+    let f = lua::eval::<Function<(), ()>>("function() end").unwrap();
+
+    {
+        let calls = Rc::clone(&calls);
+        hook::set(triggers, move |_| {
+            calls.borrow_mut().push("first");
+            let calls = Rc::clone(&calls);
+            hook::set(triggers, move |_| {
+                calls.borrow_mut().push("second");
+                hook::remove();
+                Ok(())
+            });
+            Ok(())
+        });
+    }
+
+    f.call(()).unwrap();
+    f.call(()).unwrap();
+    f.call(()).unwrap();
+
+    assert_eq!(&["first", "second"], &calls.borrow()[..]);
+}
@@ -0,0 +1,110 @@
+use nvim_oxi::lua::{self, ffi::*, utils};
+use nvim_oxi::{self as oxi, api};
+
+/// Inspects the value of the Lua global `name`.
+fn inspect_global(name: &str, depth: usize) -> String {
+    let name = std::ffi::CString::new(name).unwrap();
+    unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, name.as_ptr());
+            let inspected = utils::inspect_with_depth(lstate, -1, depth);
+            lua_pop(lstate, 1);
+            inspected
+        })
+    }
+}
+
+#[oxi::test]
+fn inspect_primitives() {
+    api::command(
+        "lua _G.primitives = { 1, 2.5, 'foo\\n', true, [10] = false }",
+    )
+    .unwrap();
+
+    assert_eq!(
+        r#"{ 1, 2.5, "foo\n", true, [10] = false }"#,
+        inspect_global("primitives", usize::MAX)
+    );
+}
+
+#[oxi::test]
+fn inspect_nested() {
+    api::command(
+        "lua _G.nested = { foo = { bar = { baz = {} } }, ['a key'] = 1 }",
+    )
+    .unwrap();
+
+    assert_eq!(
+        r#"{ ["a key"] = 1, foo = { bar = { baz = {} } } }"#,
+        inspect_global("nested", usize::MAX)
+    );
+
+    assert_eq!(
+        r#"{ ["a key"] = 1, foo = { bar = {...} } }"#,
+        inspect_global("nested", 2)
+    );
+}
+
+#[oxi::test]
+fn inspect_cycles_and_metatables() {
+    api::command(
+        "lua _G.cycle = setmetatable({}, { __name = 'cycle' }); \
+         _G.cycle.this = _G.cycle",
+    )
+    .unwrap();
+
+    assert_eq!(
+        r#"{ this = <cycle>, <metatable> = { __name = "cycle" } }"#,
+        inspect_global("cycle", usize::MAX)
+    );
+}
+
+#[oxi::test]
+fn inspect_functions() {
+    api::command("lua _G.fun = function() end").unwrap();
+    assert!(inspect_global("fun", usize::MAX).starts_with("<function: 0x"));
+}
+
+#[oxi::test]
+fn inspect_elides_long_tables() {
+    api::command(
+        "lua _G.long = {}; for i = 1, 40 do _G.long[i] = i end; _G.long.foo \
+         = 'bar'",
+    )
+    .unwrap();
+
+    let expected = (1..=32)
+        .map(|i| i.to_string())
+        .chain(std::iter::once("...".to_owned()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    assert_eq!(format!("{{ {expected} }}"), inspect_global("long", 1));
+
+    api::command("lua _G.short = { 1, 2, foo = 'bar' }").unwrap();
+    assert_eq!(r#"{ 1, 2, foo = "bar" }"#, inspect_global("short", 1));
+}
+
+#[oxi::test]
+fn inspect_caps_depth() {
+    api::command(
+        "lua _G.deep = {}; local t = _G.deep; for _ = 1, 20 do t.t = {}; t = \
+         t.t end",
+    )
+    .unwrap();
+
+    let inspected = unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, lua::macros::cstr!("deep"));
+            let inspected = utils::inspect(lstate, -1);
+            lua_pop(lstate, 1);
+            inspected
+        })
+    };
+
+    assert_eq!(
+        "{ t = { t = { t = { t = { t = { t = { t = { t = {...} } } } } } } } \
+         }",
+        inspected
+    );
+}
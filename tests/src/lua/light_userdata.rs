@@ -0,0 +1,56 @@
+use std::ffi::c_void;
+
+use nvim_oxi::lua::{self, LightUserData};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn light_userdata_roundtrip() {
+    let mut n = 42u32;
+    let ptr = LightUserData(&mut n as *mut u32 as *mut c_void);
+
+    let id = lua::eval::<Function<LightUserData, (String, LightUserData)>>(
+        "function(p) return type(p), p end",
+    )
+    .unwrap();
+    assert_eq!(Ok(("userdata".into(), ptr)), id.call(ptr));
+
+    let read = lua::eval::<Function<LightUserData, u32>>(
+        "function(p) return require('ffi').cast('uint32_t *', p)[0] end",
+    )
+    .unwrap();
+    assert_eq!(Ok(42), read.call(ptr));
+}
+
+#[oxi::test]
+fn pointer_from_cdata() {
+    let buf = lua::eval::<Function<(), (LightUserData, LightUserData)>>(
+        "function()
+            local ffi = require('ffi')
+            _G.buf = ffi.new('uint8_t[4]', { 1, 2, 3, 4 })
+            return _G.buf, ffi.cast('uint8_t *', _G.buf) + 2
+        end",
+    )
+    .unwrap();
+
+    let (array, offset) = buf.call(()).unwrap();
+    let bytes =
+        unsafe { std::slice::from_raw_parts(array.as_ptr() as *const u8, 4) };
+    assert_eq!(&[1, 2, 3, 4], bytes);
+    assert_eq!(3, unsafe { *(offset.as_ptr() as *const u8) });
+
+    // Writes from Rust are visible to Lua.
+    unsafe { *(array.as_ptr() as *mut u8) = 10 };
+    assert_eq!(Ok(10), lua::eval::<u8>("_G.buf[0]"));
+
+    lua::eval::<()>("(function() _G.buf = nil end)()").unwrap();
+
+    // Numbers and structs can't be used as pointers.
+    assert!(lua::eval::<LightUserData>("1LL").is_err());
+    assert!(
+        lua::eval::<LightUserData>("require('ffi').new('double', 1)").is_err()
+    );
+    assert!(lua::eval::<LightUserData>(
+        "require('ffi').new('struct { int x; }')"
+    )
+    .is_err());
+}
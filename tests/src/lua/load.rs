@@ -0,0 +1,40 @@
+use nvim_oxi::{self as oxi, lua};
+
+#[oxi::test]
+fn lua_eval() {
+    assert_eq!(Ok(3), lua::eval::<i32>("1 + 2"));
+    assert_eq!(Ok(3), lua::eval::<i32>("local a = 1; return a + 2"));
+    assert_eq!(Ok(()), lua::eval::<()>("_G.evaluated = true"));
+    assert_eq!(Ok(true), lua::eval::<bool>("_G.evaluated"));
+}
+
+#[oxi::test]
+fn lua_eval_errors() {
+    assert!(matches!(
+        lua::eval::<()>("local = 1"),
+        Err(lua::Error::SyntaxError(_))
+    ));
+
+    assert!(matches!(
+        lua::eval::<()>("error('boom')"),
+        Err(lua::Error::RuntimeError { .. })
+    ));
+}
+
+#[oxi::test]
+fn lua_load() {
+    let add = lua::load::<(i32, i32), i32>(
+        "local a, b = ...; return a + b",
+        "=add",
+    )
+    .unwrap();
+
+    assert_eq!(Ok(3), add.call((1, 2)));
+
+    match lua::load::<(), ()>("return +", "=broken") {
+        Err(lua::Error::SyntaxError(msg)) => {
+            assert!(msg.starts_with("broken:"), "{msg}")
+        },
+        other => panic!("{other:?}"),
+    }
+}
@@ -0,0 +1,33 @@
+use nvim_oxi::lua::{self, Table};
+use nvim_oxi::{self as oxi, Function};
+
+#[oxi::test]
+fn get_path() {
+    let upper =
+        lua::get_path::<Function<&str, String>>("string.upper").unwrap();
+    assert_eq!(Ok("FOO".into()), upper.call("foo"));
+
+    // Lazily loaded modules are loaded on first access.
+    assert!(lua::get_path::<Function<(), ()>>("vim.lsp.buf.hover").is_ok());
+
+    assert_eq!(Ok(None), lua::get_path::<Option<u8>>("vim.g.oxi_missing"));
+}
+
+#[oxi::test]
+fn get_path_missing_segment() {
+    let err = lua::get_path::<u8>("vim.oxi_missing.foo.bar").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("at `vim.oxi_missing`: can't index a nil value with `foo`"));
+
+    let err = lua::get_path::<u8>("vim.api").unwrap_err();
+    assert!(err.to_string().contains("at `vim.api`"));
+}
+
+#[oxi::test]
+fn require() {
+    let ffi = lua::require::<Table>("ffi").unwrap();
+    assert!(ffi.get::<_, Function<(), ()>>("new").is_ok());
+
+    assert!(lua::require::<Table>("oxi_missing_module").is_err());
+}
@@ -0,0 +1,15 @@
+mod borrowed;
+mod collections;
+mod coroutine;
+mod derive;
+mod function;
+mod gc;
+mod hook;
+mod inspect;
+mod light_userdata;
+mod load;
+mod lookup;
+mod registry;
+mod table;
+mod userdata;
+mod variadic;
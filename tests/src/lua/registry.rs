@@ -0,0 +1,54 @@
+use nvim_oxi::lua::{self, Poppable, RegistryRef};
+use nvim_oxi::{self as oxi, Function, Object};
+
+/// Evaluates `expr` and also stores the result in the weak table
+/// `_G.__weak[name]`, so that we can check whether it's been collected.
+fn eval_weak<T: Poppable>(name: &str, expr: &str) -> T {
+    lua::eval(&format!(
+        "(function() __weak = __weak or setmetatable({{}}, {{ __mode = 'v' \
+         }}); __weak['{name}'] = {expr}; return __weak['{name}'] end)()"
+    ))
+    .unwrap()
+}
+
+fn is_collected(name: &str) -> bool {
+    lua::eval(&format!(
+        "(function() collectgarbage(); return __weak['{name}'] == nil end)()"
+    ))
+    .unwrap()
+}
+
+#[oxi::test]
+fn registry_ref_clone_and_eq() {
+    let table = lua::eval::<RegistryRef>("{}").unwrap();
+    let other = lua::eval::<RegistryRef>("{}").unwrap();
+
+    let clone = table.clone();
+    assert_ne!(table.as_raw(), clone.as_raw());
+    assert_eq!(table, clone);
+    assert_ne!(table, other);
+}
+
+#[oxi::test]
+fn registry_ref_released_on_drop() {
+    let table = eval_weak::<RegistryRef>("table", "{}");
+    let clone = table.clone();
+
+    drop(table);
+    assert!(!is_collected("table"));
+
+    drop(clone);
+    assert!(is_collected("table"));
+}
+
+#[oxi::test]
+fn function_released_on_drop() {
+    let fun = eval_weak::<Function<(), ()>>("fun", "function() end");
+    let obj = Object::from(fun.clone());
+
+    drop(fun);
+    assert!(!is_collected("fun"));
+
+    drop(obj);
+    assert!(is_collected("fun"));
+}
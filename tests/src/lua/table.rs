@@ -0,0 +1,85 @@
+use nvim_oxi::lua::{self, ffi::*, macros::cstr, Poppable, Table};
+use nvim_oxi::{self as oxi, api};
+
+fn global_table(name: *const std::ffi::c_char) -> Table {
+    unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, name);
+            Table::pop(lstate).unwrap()
+        })
+    }
+}
+
+#[oxi::test]
+fn table_get_set() {
+    let table = Table::new();
+    assert_eq!(Ok(()), table.set("foo", 42));
+    assert_eq!(Ok(42), table.get::<_, i32>("foo"));
+    assert_eq!(Ok(None), table.get::<_, Option<i32>>("bar"));
+    assert_eq!(0, table.raw_len());
+}
+
+#[oxi::test]
+fn table_pairs_ipairs() {
+    api::command("lua _G.t = { 'a', 'b', 'c', [5] = 'e' }").unwrap();
+
+    let table = global_table(cstr!("t"));
+    assert_eq!(3, table.raw_len());
+
+    let values = table.ipairs::<String>().collect::<Result<Vec<_>, _>>();
+    assert_eq!(Ok(vec!["a".into(), "b".into(), "c".into()]), values);
+
+    let pairs = table
+        .pairs::<lua::ffi::lua_Integer, String>()
+        .collect::<Result<Vec<_>, _>>();
+    assert_eq!(
+        Ok(vec![
+            (1, "a".into()),
+            (2, "b".into()),
+            (3, "c".into()),
+            (5, "e".into())
+        ]),
+        pairs
+    );
+}
+
+#[oxi::test]
+fn table_metatable() {
+    api::command(
+        "lua _G.t = setmetatable({}, { __index = function() return 7 end })",
+    )
+    .unwrap();
+
+    let table = global_table(cstr!("t"));
+    assert!(table.metatable().is_some());
+    assert_eq!(Ok(7), table.get::<_, i32>("anything"));
+    assert_eq!(Ok(None), table.raw_get::<_, Option<i32>>("anything"));
+
+    table.set_metatable(None);
+    assert!(table.metatable().is_none());
+}
+
+#[oxi::test]
+fn table_errors() {
+    api::command(
+        "lua _G.t = setmetatable({}, { __index = function() error('oops') \
+         end })",
+    )
+    .unwrap();
+
+    let table = global_table(cstr!("t"));
+    assert!(matches!(
+        table.get::<_, i32>("foo"),
+        Err(lua::Error::RuntimeError { .. })
+    ));
+    assert_eq!(Ok(None), table.raw_get::<_, Option<i32>>("foo"));
+
+    assert!(matches!(
+        table.raw_set(f64::NAN, 1),
+        Err(lua::Error::RuntimeError { .. })
+    ));
+    assert!(matches!(
+        table.set(None::<i32>, 1),
+        Err(lua::Error::RuntimeError { .. })
+    ));
+}
@@ -0,0 +1,92 @@
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use nvim_oxi::lua::{self, ffi::*, macros::cstr, Poppable, Pushable};
+use nvim_oxi::lua::{MetaMethod, UserData, UserDataMethods, UserDataRef};
+use nvim_oxi::{self as oxi, api};
+
+struct Counter {
+    count: u32,
+    dropped: Rc<Cell<bool>>,
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.dropped.set(true);
+    }
+}
+
+impl UserData for Counter {
+    fn add_methods(methods: &mut UserDataMethods<Self>) {
+        methods.add_method("get", |this, ()| Ok::<_, Infallible>(this.count));
+
+        methods.add_method_mut("incr", |this, by: Option<u32>| {
+            this.count += by.unwrap_or(1);
+            Ok::<_, Infallible>(())
+        });
+
+        methods.add_meta_method(MetaMethod::ToString, |this, ()| {
+            Ok::<_, Infallible>(format!("Counter({})", this.count))
+        });
+
+        methods.add_meta_method(MetaMethod::Index, |_, key: String| {
+            Ok::<_, Infallible>(key.len())
+        });
+    }
+}
+
+fn set_global<T: Pushable>(name: *const std::ffi::c_char, value: T) {
+    unsafe {
+        lua::with_state(|lstate| {
+            value.push(lstate).unwrap();
+            lua_setglobal(lstate, name);
+        })
+    }
+}
+
+fn get_global<T: Poppable>(name: *const std::ffi::c_char) -> T {
+    unsafe {
+        lua::with_state(|lstate| {
+            lua_getglobal(lstate, name);
+            T::pop(lstate).unwrap()
+        })
+    }
+}
+
+#[oxi::test]
+fn userdata_methods() {
+    let dropped = Rc::new(Cell::new(false));
+    let counter = UserDataRef::new(Counter { count: 0, dropped });
+    set_global(cstr!("counter"), counter.clone());
+
+    api::command("lua counter:incr(); counter:incr(2)").unwrap();
+    assert_eq!(3, counter.borrow().unwrap().count);
+
+    api::command("lua _G.n = counter:get()").unwrap();
+    assert_eq!(3, get_global::<u32>(cstr!("n")));
+
+    api::command("lua _G.s = tostring(counter)").unwrap();
+    assert_eq!("Counter(3)", get_global::<String>(cstr!("s")));
+
+    api::command("lua _G.n = counter.hello").unwrap();
+    assert_eq!(5, get_global::<u32>(cstr!("n")));
+
+    let popped = get_global::<UserDataRef<Counter>>(cstr!("counter"));
+    popped.borrow_mut().unwrap().count = 10;
+    assert_eq!(10, counter.borrow().unwrap().count);
+
+    let _borrow = counter.borrow().unwrap();
+    assert!(api::command("lua counter:incr()").is_err());
+}
+
+#[oxi::test]
+fn userdata_dropped_on_gc() {
+    let dropped = Rc::new(Cell::new(false));
+    let counter = Counter { count: 0, dropped: dropped.clone() };
+    set_global(cstr!("counter"), UserDataRef::new(counter));
+    assert!(!dropped.get());
+
+    api::command("lua counter = nil; collectgarbage()").unwrap();
+    assert!(dropped.get());
+}
@@ -0,0 +1,50 @@
+use nvim_oxi::lua::{self, Variadic};
+use nvim_oxi::{self as oxi, Function, MultiValue, Object};
+
+#[oxi::test]
+fn variadic_args() {
+    let sum = Function::from_fn(|(init, nums): (i32, Variadic<i32>)| {
+        Ok::<_, oxi::Error>(init + nums.iter().sum::<i32>())
+    });
+
+    assert_eq!(Ok(1), sum.call((1, Variadic::new())));
+    assert_eq!(Ok(10), sum.call((1, Variadic::from(vec![2, 3, 4]))));
+}
+
+#[oxi::test]
+fn tuple_push_count() {
+    let count = lua::load::<((), i32, Variadic<i32>, i32), usize>(
+        "return select('#', ...)",
+        "=count",
+    )
+    .unwrap();
+
+    assert_eq!(Ok(2), count.call(((), 1, Variadic::new(), 2)));
+    assert_eq!(Ok(4), count.call(((), 1, Variadic::from(vec![2, 3]), 4)));
+}
+
+#[oxi::test]
+fn variadic_returns() {
+    let f = lua::load::<(), Variadic<i32>>("return 1, 2, 3", "=f").unwrap();
+    assert_eq!(Ok(vec![1, 2, 3]), f.call(()).map(Variadic::into_inner));
+
+    let none = lua::load::<(), Variadic<i32>>("return", "=none").unwrap();
+    assert_eq!(Ok(Vec::new()), none.call(()).map(Variadic::into_inner));
+}
+
+#[oxi::test]
+fn multi_value_returns() {
+    let f = Function::<(), MultiValue>::from_fn(|()| {
+        Ok::<_, oxi::Error>(
+            vec![Object::from(1), Object::from("two"), Object::nil()].into(),
+        )
+    });
+
+    let count = lua::load::<Function<(), MultiValue>, usize>(
+        "local f = ...; return select('#', f())",
+        "=count",
+    )
+    .unwrap();
+
+    assert_eq!(Ok(3), count.call(f));
+}